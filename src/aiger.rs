@@ -0,0 +1,617 @@
+use crate::circuit::{Circuit, Gate, GateType};
+use crate::error::{Error, Result};
+use crate::yosys::PortAssignment;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Combinational and-inverter graph in AIGER numbering
+///
+/// Variable 0 is the constant false, and a literal is twice its variable
+/// plus one when negated.
+#[derive(Default)]
+struct Aig {
+    max_variable: u32,
+    inputs: Vec<u32>,
+    outputs: Vec<u32>,
+    /// Output literal and the two input literals of every AND gate
+    ands: Vec<[u32; 3]>,
+    input_names: HashMap<usize, String>,
+    output_names: HashMap<usize, String>,
+}
+
+/// Literal of a positive variable, failing when it does not fit a `u32`
+/// together with its negation
+fn positive_literal(line: usize, variable: u32) -> Result<u32> {
+    variable
+        .checked_mul(2)
+        .filter(|&literal| literal < u32::MAX)
+        .ok_or_else(|| Error::syntax(line, format!("variable {variable} is out of range")))
+}
+
+/// Cursor over an AIGER file, which mixes text lines with the binary
+/// encoding of AND gates
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+    line: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn next_line(&mut self) -> Option<Result<&'a str>> {
+        let rest = self
+            .data
+            .get(self.position..)
+            .filter(|rest| !rest.is_empty())?;
+        let end = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .unwrap_or(rest.len());
+        self.position += end + 1;
+        self.line += 1;
+        Some(
+            std::str::from_utf8(&rest[..end])
+                .map(|line| line.trim_end_matches('\r'))
+                .map_err(|_| Error::syntax(self.line, "invalid UTF-8")),
+        )
+    }
+
+    /// Next line, which must hold exactly `count` numbers
+    fn numbers(&mut self, count: usize) -> Result<Vec<u32>> {
+        let line = self
+            .next_line()
+            .ok_or_else(|| Error::syntax(self.line + 1, "unexpected end of file"))??;
+        let numbers = line
+            .split_whitespace()
+            .map(|token| {
+                token.parse().map_err(|_| {
+                    Error::syntax(self.line, format!("expected a number, found `{token}`"))
+                })
+            })
+            .collect::<Result<Vec<u32>>>()?;
+        if numbers.len() != count {
+            return Err(Error::syntax(
+                self.line,
+                format!("expected {count} numbers"),
+            ));
+        }
+        Ok(numbers)
+    }
+
+    /// Unsigned number in 7-bit groups, least significant group first
+    fn varint(&mut self) -> Result<u32> {
+        let mut value = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| Error::syntax(self.line, "truncated AND gates"))?;
+            self.position += 1;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::syntax(self.line, "AND gate delta overflows"))
+    }
+}
+
+impl Aig {
+    fn parse(data: &[u8]) -> Result<Aig> {
+        let mut reader = Reader {
+            data,
+            position: 0,
+            line: 0,
+        };
+        let header = reader
+            .next_line()
+            .ok_or_else(|| Error::syntax(1, "empty file"))??;
+        let mut tokens = header.split_whitespace();
+        let binary = match tokens.next() {
+            Some("aag") => false,
+            Some("aig") => true,
+            _ => return Err(Error::syntax(1, "expected an `aag` or `aig` header")),
+        };
+        let counts = tokens
+            .map(|token| {
+                token
+                    .parse::<u32>()
+                    .map_err(|_| Error::syntax(1, format!("expected a number, found `{token}`")))
+            })
+            .collect::<Result<Vec<u32>>>()?;
+        let [max_variable, input_count, latch_count, output_count, and_count, ref extra @ ..] =
+            counts[..]
+        else {
+            return Err(Error::syntax(1, "expected M I L O A counts"));
+        };
+        if latch_count > 0 {
+            return Err(Error::Import("latches are not combinational".to_string()));
+        }
+        if extra.iter().any(|&count| count > 0) {
+            return Err(Error::Import(
+                "bad state, constraint, justice and fairness properties are not supported"
+                    .to_string(),
+            ));
+        }
+        positive_literal(1, max_variable)?;
+        if input_count
+            .checked_add(and_count)
+            .is_none_or(|count| count > max_variable)
+        {
+            return Err(Error::syntax(1, "more inputs and AND gates than variables"));
+        }
+
+        let mut aig = Aig {
+            max_variable,
+            ..Aig::default()
+        };
+        let check = |line: usize, literal: u32| {
+            if literal > 2 * max_variable + 1 {
+                return Err(Error::syntax(
+                    line,
+                    format!("literal {literal} is out of range"),
+                ));
+            }
+            Ok(literal)
+        };
+        for variable in 1..=input_count {
+            let literal = if binary {
+                2 * variable
+            } else {
+                reader.numbers(1)?[0]
+            };
+            if literal < 2 || literal & 1 == 1 {
+                return Err(Error::syntax(
+                    reader.line,
+                    "inputs must be positive variables",
+                ));
+            }
+            aig.inputs.push(check(reader.line, literal)?);
+        }
+        for _ in 0..output_count {
+            let literal = reader.numbers(1)?[0];
+            aig.outputs.push(check(reader.line, literal)?);
+        }
+        for index in 0..and_count {
+            let gate = if binary {
+                let lhs = positive_literal(reader.line, input_count + index + 1)?;
+                let (delta0, delta1) = (reader.varint()?, reader.varint()?);
+                let rhs0 = lhs.checked_sub(delta0);
+                let rhs1 = rhs0.and_then(|rhs0| rhs0.checked_sub(delta1));
+                match (rhs0, rhs1) {
+                    (Some(rhs0), Some(rhs1)) => [lhs, rhs0, rhs1],
+                    _ => return Err(Error::syntax(reader.line, "invalid AND gate delta")),
+                }
+            } else {
+                let numbers = reader.numbers(3)?;
+                [numbers[0], numbers[1], numbers[2]]
+            };
+            if gate[0] < 2 || gate[0] & 1 == 1 {
+                return Err(Error::syntax(
+                    reader.line,
+                    "AND gates must define a positive variable",
+                ));
+            }
+            for literal in gate {
+                check(reader.line, literal)?;
+            }
+            aig.ands.push(gate);
+        }
+
+        // Optional symbol table, ended by the comment section
+        while let Some(line) = reader.next_line() {
+            let line = line?;
+            if line == "c" {
+                break;
+            }
+            let (symbol, name) = line.split_once(' ').unwrap_or((line, ""));
+            let (kind, position) = symbol.split_at(symbol.len().min(1));
+            let position: usize = position
+                .parse()
+                .map_err(|_| Error::syntax(reader.line, format!("invalid symbol `{symbol}`")))?;
+            match kind {
+                "i" => aig.input_names.insert(position, name.to_string()),
+                "o" => aig.output_names.insert(position, name.to_string()),
+                _ => {
+                    return Err(Error::syntax(
+                        reader.line,
+                        format!("invalid symbol `{symbol}`"),
+                    ))
+                }
+            };
+        }
+        Ok(aig)
+    }
+
+    /// Circuit with one gate per AND gate, the negations of its inputs folded
+    /// into the gate type
+    ///
+    /// Variables keep their number as wire id. Negated outputs get a NOT gate
+    /// and the constant gets a constant wire, numbered past the variables.
+    fn into_circuit(self, id: &str, ports: &PortAssignment) -> Result<Circuit> {
+        let input_names: Vec<String> = (0..self.inputs.len())
+            .map(|k| self.input_names.get(&k).cloned().unwrap_or(format!("i{k}")))
+            .collect();
+        let output_names: Vec<String> = (0..self.outputs.len())
+            .map(|k| {
+                self.output_names
+                    .get(&k)
+                    .cloned()
+                    .unwrap_or(format!("o{k}"))
+            })
+            .collect();
+        let input_refs: Vec<&str> = input_names.iter().map(String::as_str).collect();
+        let output_refs: Vec<&str> = output_names.iter().map(String::as_str).collect();
+        let (alice_names, bob_names) = ports.parties(&input_refs)?;
+
+        let party = |names: Vec<&str>| -> Result<Option<Vec<u32>>> {
+            let wires = names
+                .into_iter()
+                .map(
+                    |name| match input_refs.iter().position(|input| *input == name) {
+                        Some(k) => Ok(self.inputs[k] >> 1),
+                        None => Err(Error::Import(format!("no input `{name}`"))),
+                    },
+                )
+                .collect::<Result<Vec<u32>>>()?;
+            Ok((!wires.is_empty()).then_some(wires))
+        };
+        let alice = party(alice_names)?;
+        let bob = party(bob_names)?;
+
+        let zero = self.max_variable + 1;
+        let wire = |literal: u32| match literal >> 1 {
+            0 => zero,
+            variable => variable,
+        };
+        let mut gates: Vec<Gate> = self
+            .ands
+            .iter()
+            .map(|&[lhs, rhs0, rhs1]| {
+                let gate_type = match (rhs0 & 1, rhs1 & 1) {
+                    (0, 0) => GateType::And,
+                    (0, _) => GateType::NImply,
+                    (_, 0) => GateType::CNImply,
+                    _ => GateType::Nor,
+                };
+                Gate::new(lhs >> 1, gate_type, vec![wire(rhs0), wire(rhs1)])
+            })
+            .collect();
+
+        let mut out = Vec::new();
+        let mut negations: HashMap<u32, u32> = HashMap::new();
+        for name in ports.outputs(&output_refs) {
+            let k = output_refs
+                .iter()
+                .position(|output| *output == name)
+                .ok_or_else(|| Error::Import(format!("no output `{name}`")))?;
+            let literal = self.outputs[k];
+            if literal & 1 == 0 {
+                out.push(wire(literal));
+                continue;
+            }
+            let next = zero + 1 + negations.len() as u32;
+            let negation = *negations.entry(wire(literal)).or_insert(next);
+            if negation == next {
+                gates.push(Gate::new(next, GateType::Not, vec![wire(literal)]));
+            }
+            out.push(negation);
+        }
+
+        let mut constants = HashMap::new();
+        let uses_zero = out.contains(&zero) || gates.iter().any(|gate| gate.inputs.contains(&zero));
+        if uses_zero {
+            constants.insert(zero, 0);
+        }
+        let circuit = Circuit {
+            id: id.to_string(),
+            gates,
+            alice,
+            bob,
+            out,
+            constants,
+        };
+        circuit.validate()?;
+        Ok(circuit)
+    }
+
+    fn header(&self, kind: &str) -> String {
+        format!(
+            "{kind} {} {} 0 {} {}\n",
+            self.max_variable,
+            self.inputs.len(),
+            self.outputs.len(),
+            self.ands.len()
+        )
+    }
+
+    fn symbols(&self) -> String {
+        let mut symbols = String::new();
+        for (kind, names, count) in [
+            ("i", &self.input_names, self.inputs.len()),
+            ("o", &self.output_names, self.outputs.len()),
+        ] {
+            for k in (0..count).filter(|k| names.contains_key(k)) {
+                symbols += &format!("{kind}{k} {}\n", names[&k]);
+            }
+        }
+        symbols
+    }
+
+    fn to_ascii(&self) -> String {
+        let mut text = self.header("aag");
+        for literal in self.inputs.iter().chain(&self.outputs) {
+            text += &format!("{literal}\n");
+        }
+        for [lhs, rhs0, rhs1] in &self.ands {
+            text += &format!("{lhs} {rhs0} {rhs1}\n");
+        }
+        text + &self.symbols()
+    }
+
+    /// Binary AIGER, which needs inputs numbered first and every AND gate's
+    /// inputs below its output with the larger one first
+    fn to_binary(&self) -> Vec<u8> {
+        let mut data = self.header("aig").into_bytes();
+        for literal in &self.outputs {
+            data.extend(format!("{literal}\n").bytes());
+        }
+        for &[lhs, rhs0, rhs1] in &self.ands {
+            for mut delta in [lhs - rhs0, rhs0 - rhs1] {
+                while delta >= 0x80 {
+                    data.push((delta & 0x7f) as u8 | 0x80);
+                    delta >>= 7;
+                }
+                data.push(delta as u8);
+            }
+        }
+        data.extend(self.symbols().bytes());
+        data
+    }
+}
+
+/// Builds the AND gates of an AIG after its inputs, folding constants and
+/// sharing structurally identical gates
+struct AigBuilder {
+    input_count: u32,
+    ands: Vec<[u32; 3]>,
+    existing: HashMap<(u32, u32), u32>,
+}
+
+impl AigBuilder {
+    fn and(&mut self, a: u32, b: u32) -> Result<u32> {
+        let (high, low) = (a.max(b), a.min(b));
+        if low == 0 || high == low ^ 1 {
+            return Ok(0);
+        }
+        if low == 1 || high == low {
+            return Ok(high);
+        }
+        if let Some(&literal) = self.existing.get(&(high, low)) {
+            return Ok(literal);
+        }
+        let variable = u32::try_from(self.ands.len())
+            .ok()
+            .and_then(|ands| self.input_count.checked_add(ands)?.checked_add(1))
+            .ok_or_else(|| Error::syntax(0, "too many AND gates"))?;
+        let literal = positive_literal(0, variable)?;
+        self.ands.push([literal, high, low]);
+        self.existing.insert((high, low), literal);
+        Ok(literal)
+    }
+
+    fn or(&mut self, a: u32, b: u32) -> Result<u32> {
+        Ok(self.and(a ^ 1, b ^ 1)? ^ 1)
+    }
+
+    /// `select ? high : low`, with the constant cases taking a single gate
+    fn mux(&mut self, select: u32, high: u32, low: u32) -> Result<u32> {
+        match (high, low) {
+            _ if high == low => Ok(high),
+            (_, 0) => self.and(select, high),
+            (0, _) => self.and(select ^ 1, low),
+            (1, _) => self.or(select, low),
+            (_, 1) => self.or(select ^ 1, high),
+            _ => {
+                let when_high = self.and(select, high)?;
+                let when_low = self.and(select ^ 1, low)?;
+                self.or(when_high, when_low)
+            }
+        }
+    }
+
+    /// Literal computing a truth table over the input literals by Shannon
+    /// expansion on the first input, the most significant bit of the row
+    fn table(&mut self, inputs: &[u32], table: &[u8]) -> Result<u32> {
+        let Some((&select, rest)) = inputs.split_first() else {
+            return Ok(table[0] as u32);
+        };
+        let (low, high) = table.split_at(table.len() / 2);
+        let low = self.table(rest, low)?;
+        let high = self.table(rest, high)?;
+        self.mux(select, high, low)
+    }
+}
+
+impl Circuit {
+    /// Parse an AIGER file, either ASCII (`aag`) or binary (`aig`)
+    ///
+    /// Only combinational files are accepted. Inputs and outputs are named by
+    /// the symbol table, or `i<k>` and `o<k>` without one, and assigned to
+    /// the parties by `ports`.
+    pub fn from_aiger(id: &str, data: &[u8], ports: &PortAssignment) -> Result<Circuit> {
+        Aig::parse(data)?.into_circuit(id, ports)
+    }
+
+    /// Load an AIGER file, named after the file stem
+    pub fn load_aiger<P: AsRef<Path>>(path: P, ports: &PortAssignment) -> Result<Circuit> {
+        let path = path.as_ref();
+        let id = path.file_stem().unwrap_or_default().to_string_lossy();
+        Circuit::from_aiger(&id, &fs::read(path)?, ports)
+    }
+
+    /// Write the circuit as ASCII AIGER
+    ///
+    /// Every gate becomes AND gates over possibly negated literals. Inputs
+    /// are named `alice_<wire>` and `bob_<wire>` and outputs `out_<wire>`,
+    /// so reading the file back assigns the inputs to the same parties.
+    pub fn to_aiger(&self) -> Result<String> {
+        Ok(self.to_aig()?.to_ascii())
+    }
+
+    /// Write the circuit as binary AIGER, see `to_aiger`
+    pub fn to_aiger_binary(&self) -> Result<Vec<u8>> {
+        Ok(self.to_aig()?.to_binary())
+    }
+
+    fn to_aig(&self) -> Result<Aig> {
+        let order = self.topological_order()?;
+        let mut aig = Aig::default();
+        let mut literals: HashMap<u32, u32> = HashMap::new();
+        let parties = [("alice", &self.alice), ("bob", &self.bob)];
+        for (party, wires) in parties {
+            for &wire in wires.iter().flatten() {
+                let literal = 2 * (aig.inputs.len() as u32 + 1);
+                aig.input_names
+                    .insert(aig.inputs.len(), format!("{party}_{wire}"));
+                aig.inputs.push(literal);
+                literals.insert(wire, literal);
+            }
+        }
+        for (&wire, &value) in &self.constants {
+            literals.insert(wire, value as u32);
+        }
+
+        let mut builder = AigBuilder {
+            input_count: aig.inputs.len() as u32,
+            ands: Vec::new(),
+            existing: HashMap::new(),
+        };
+        for gate in order {
+            let inputs: Vec<u32> = gate.inputs.iter().map(|wire| literals[wire]).collect();
+            let literal = builder.table(&inputs, &gate.truth_table())?;
+            literals.insert(gate.id, literal);
+        }
+        for (k, wire) in self.out.iter().enumerate() {
+            aig.outputs.push(literals[wire]);
+            aig.output_names.insert(k, format!("out_{wire}"));
+        }
+        aig.max_variable = builder.input_count + builder.ands.len() as u32;
+        aig.ands = builder.ands;
+        Ok(aig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::CircuitError;
+    use crate::test_support::{assert_equivalent, evaluate, round_trip_circuits};
+
+    #[test]
+    fn test_aiger_round_trip() {
+        for circuit in round_trip_circuits() {
+            let ports = PortAssignment::default();
+            let text = circuit.to_aiger().unwrap();
+            let ascii = Circuit::from_aiger(&circuit.id, text.as_bytes(), &ports).unwrap();
+            assert_equivalent(&circuit, &ascii);
+            assert_eq!(
+                ascii.alice.iter().flatten().count(),
+                circuit.alice.iter().flatten().count()
+            );
+
+            let data = circuit.to_aiger_binary().unwrap();
+            let binary = Circuit::from_aiger(&circuit.id, &data, &ports).unwrap();
+            assert_equivalent(&circuit, &binary);
+            assert_eq!(ascii.gates.len(), binary.gates.len());
+        }
+    }
+
+    #[test]
+    fn test_aiger_and_count() {
+        // XOR takes three AND gates and every AND-like gate one
+        let circuits = Circuit::from_json_file("circuits/bool.json").unwrap();
+        let and_counts: Vec<usize> = circuits
+            .iter()
+            .map(|circuit| {
+                Aig::parse(circuit.to_aiger().unwrap().as_bytes())
+                    .unwrap()
+                    .ands
+                    .len()
+            })
+            .collect();
+        assert_eq!(and_counts, vec![1, 1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn test_parse_aiger() {
+        // The AIGER specification's half adder, with named inputs
+        let text = "aag 7 2 0 2 3\n2\n4\n6\n12\n6 13 15\n12 2 4\n14 3 5\ni0 x\ni1 y\no0 s\no1 c\n";
+        let ports: PortAssignment =
+            serde_json::from_str(r#"{"alice": ["x"], "bob": ["y"]}"#).unwrap();
+        let circuit = Circuit::from_aiger("half_adder", text.as_bytes(), &ports).unwrap();
+        assert_eq!(circuit.alice, Some(vec![1]));
+        assert_eq!(circuit.bob, Some(vec![2]));
+        for x in 0..2 {
+            for y in 0..2 {
+                assert_eq!(evaluate(&circuit, &[x, y]), vec![x ^ y, x & y]);
+            }
+        }
+
+        // Without symbols, inputs are named by position
+        let text = "aag 3 2 0 1 1\n2\n4\n7\n6 2 5\n";
+        let ports: PortAssignment =
+            serde_json::from_str(r#"{"alice": ["i0"], "bob": ["i1"]}"#).unwrap();
+        let circuit = Circuit::from_aiger("nimply", text.as_bytes(), &ports).unwrap();
+        for x in 0..2 {
+            for y in 0..2 {
+                assert_eq!(evaluate(&circuit, &[x, y]), vec![1 ^ (x & (1 ^ y))]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aiger_errors() {
+        let ports = PortAssignment::default();
+        let latch = "aag 1 0 1 0 0\n2 3\n";
+        assert!(matches!(
+            Circuit::from_aiger("latch", latch.as_bytes(), &ports),
+            Err(Error::Import(_))
+        ));
+        let truncated = b"aig 3 2 0 1 1\n6\n\x02";
+        assert!(matches!(
+            Circuit::from_aiger("truncated", truncated, &ports),
+            Err(Error::Syntax { message, .. }) if message.contains("truncated")
+        ));
+        let out_of_range = "aag 1 1 0 1 0\n2\n9\ni0 alice\n";
+        assert!(matches!(
+            Circuit::from_aiger("range", out_of_range.as_bytes(), &ports),
+            Err(Error::Syntax { line: 3, .. })
+        ));
+
+        // Header counts whose literals or sums overflow
+        for header in ["aag 4294967295 1 0 0 0\n", "aig 100 4294967295 0 0 2\n"] {
+            assert!(matches!(
+                Circuit::from_aiger("overflow", header.as_bytes(), &ports),
+                Err(Error::Syntax { line: 1, .. })
+            ));
+        }
+
+        // A constant that is not a bit would otherwise become a literal
+        let constant = Circuit {
+            id: "constant".to_string(),
+            gates: vec![Gate::new(6, GateType::And, vec![1, 5])],
+            alice: Some(vec![1]),
+            bob: None,
+            out: vec![6],
+            constants: HashMap::from([(5, 2)]),
+        };
+        assert!(matches!(
+            constant.to_aiger(),
+            Err(Error::MalformedCircuit(CircuitError::InvalidConstant {
+                wire: 5,
+                value: 2
+            }))
+        ));
+    }
+}
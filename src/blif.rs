@@ -0,0 +1,349 @@
+use crate::circuit::{Circuit, Gate, GateType, MAX_LUT_INPUTS};
+use crate::error::{Error, Result};
+use crate::yosys::PortAssignment;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Logical lines of a BLIF file with the number of their first line, without
+/// comments and with continued lines joined
+fn logical_lines(text: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim_end();
+        let (line, continued) = match line.strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let (number, mut joined) = pending.take().unwrap_or((index + 1, String::new()));
+        joined.push_str(line);
+        joined.push(' ');
+        if continued {
+            pending = Some((number, joined));
+        } else if !joined.trim().is_empty() {
+            lines.push((number, joined));
+        }
+    }
+    lines.extend(pending.filter(|(_, joined)| !joined.trim().is_empty()));
+    lines
+}
+
+/// `.names` block: its nets, the output last, and its cover rows
+struct Cover<'a> {
+    nets: Vec<&'a str>,
+    rows: Vec<(usize, Vec<&'a str>)>,
+}
+
+impl Cover<'_> {
+    /// Output for every input combination, the first input being the most
+    /// significant bit of the row index
+    fn table(&self) -> Result<Vec<u8>> {
+        let arity = self.nets.len() - 1;
+        if arity > MAX_LUT_INPUTS {
+            return Err(Error::Import(format!(
+                "`{}` has more than {MAX_LUT_INPUTS} inputs",
+                self.nets[arity]
+            )));
+        }
+        // Rows list either the on-set or the off-set of the function
+        let on_set = self.rows.first().map_or("1", |(_, row)| row[row.len() - 1]);
+        let mut table = vec![(on_set == "0") as u8; 1 << arity];
+        for (line, row) in &self.rows {
+            let (cube, output) = match row[..] {
+                [output] if arity == 0 => ("", output),
+                [cube, output] if cube.len() == arity => (cube, output),
+                _ => {
+                    return Err(Error::syntax(
+                        *line,
+                        format!("expected a cube of {arity} inputs"),
+                    ))
+                }
+            };
+            if !matches!(output, "0" | "1") {
+                return Err(Error::syntax(*line, "invalid output value"));
+            }
+            if output != on_set {
+                return Err(Error::syntax(*line, "rows mix the on-set and off-set"));
+            }
+            if let Some(bad) = cube.chars().find(|c| !matches!(c, '0' | '1' | '-')) {
+                return Err(Error::syntax(
+                    *line,
+                    format!("invalid cube character `{bad}`"),
+                ));
+            }
+            for (index, entry) in table.iter_mut().enumerate() {
+                let covered = cube.bytes().enumerate().all(|(i, c)| {
+                    let bit = (index >> (arity - 1 - i)) & 1;
+                    c == b'-' || (c - b'0') as usize == bit
+                });
+                if covered {
+                    *entry = (on_set == "1") as u8;
+                }
+            }
+        }
+        Ok(table)
+    }
+}
+
+/// `.names` block computing `table` over the input nets, one row per input
+/// combination in the on-set
+fn names_block(inputs: &[&str], output: &str, table: &[u8]) -> String {
+    let arity = inputs.len();
+    let mut block = format!(".names {}\n", [inputs, &[output]].concat().join(" "));
+    for (index, _) in table.iter().enumerate().filter(|(_, &bit)| bit == 1) {
+        let cube: String = (0..arity)
+            .map(|i| char::from(b'0' + ((index >> (arity - 1 - i)) & 1) as u8))
+            .collect();
+        if arity == 0 {
+            block += "1\n";
+        } else {
+            block += &format!("{cube} 1\n");
+        }
+    }
+    block
+}
+
+impl Circuit {
+    /// Parse the first model of a combinational BLIF file
+    ///
+    /// Every `.names` block becomes a gate, a LUT when its function is not a
+    /// fixed gate type. Inputs and outputs are assigned by `ports`, and the
+    /// circuit is named after the model.
+    pub fn from_blif(text: &str, ports: &PortAssignment) -> Result<Circuit> {
+        let lines = logical_lines(text);
+        let mut name = "blif";
+        let mut inputs: Vec<&str> = Vec::new();
+        let mut outputs: Vec<&str> = Vec::new();
+        let mut covers: Vec<Cover> = Vec::new();
+        let mut in_cover = false;
+        for (line, content) in &lines {
+            let tokens: Vec<&str> = content.split_whitespace().collect();
+            let cover_row = in_cover && !tokens[0].starts_with('.');
+            in_cover = cover_row || tokens[0] == ".names";
+            match tokens[0] {
+                ".model" => name = tokens.get(1).copied().unwrap_or(name),
+                ".inputs" => inputs.extend(&tokens[1..]),
+                ".outputs" => outputs.extend(&tokens[1..]),
+                ".names" if tokens.len() == 1 => {
+                    return Err(Error::syntax(*line, "`.names` needs an output net"))
+                }
+                ".names" => covers.push(Cover {
+                    nets: tokens[1..].to_vec(),
+                    rows: Vec::new(),
+                }),
+                ".end" => break,
+                ".latch" | ".mlatch" => {
+                    return Err(Error::Import("latches are not combinational".to_string()))
+                }
+                directive if directive.starts_with('.') => {
+                    return Err(Error::Import(format!("`{directive}` is not supported")))
+                }
+                _ if cover_row => covers.last_mut().unwrap().rows.push((*line, tokens)),
+                _ => return Err(Error::syntax(*line, "cover row outside `.names`")),
+            }
+        }
+
+        let mut wires: HashMap<&str, u32> = HashMap::new();
+        let mut wire = |net| {
+            let next = wires.len() as u32;
+            *wires.entry(net).or_insert(next)
+        };
+        let (alice, bob) = ports.parties(&inputs)?;
+        let alice: Vec<u32> = alice.into_iter().map(&mut wire).collect();
+        let bob: Vec<u32> = bob.into_iter().map(&mut wire).collect();
+        for &input in &inputs {
+            wire(input);
+        }
+
+        let mut gates = Vec::new();
+        for cover in &covers {
+            let table = cover.table()?;
+            let (&output, inputs) = cover.nets.split_last().unwrap();
+            let id = wire(output);
+            let inputs: Vec<u32> = inputs.iter().map(|&net| wire(net)).collect();
+            let fixed = (inputs.len() <= 2)
+                .then(|| {
+                    let packed = table
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |packed, (index, &bit)| packed | bit << index);
+                    GateType::from_truth_table(inputs.len(), packed)
+                })
+                .flatten();
+            gates.push(match fixed {
+                Some(gate_type) => Gate::new(id, gate_type, inputs),
+                None => Gate::lut(id, inputs, table),
+            });
+        }
+
+        let out = ports
+            .outputs(&outputs)
+            .into_iter()
+            .map(|net| match wires.get(net) {
+                Some(&wire) => Ok(wire),
+                None => Err(Error::Import(format!("output `{net}` is not driven"))),
+            })
+            .collect::<Result<Vec<u32>>>()?;
+        let circuit = Circuit {
+            id: name.to_string(),
+            gates,
+            alice: (!alice.is_empty()).then_some(alice),
+            bob: (!bob.is_empty()).then_some(bob),
+            out,
+            constants: HashMap::new(),
+        };
+        circuit.validate()?;
+        Ok(circuit)
+    }
+
+    /// Load the first model of a BLIF file
+    pub fn load_blif<P: AsRef<Path>>(path: P, ports: &PortAssignment) -> Result<Circuit> {
+        Circuit::from_blif(&fs::read_to_string(path)?, ports)
+    }
+
+    /// Write the circuit as a BLIF model with one `.names` block per gate
+    ///
+    /// Inputs are named `alice_<wire>` and `bob_<wire>`, so reading the file
+    /// back assigns them to the same parties. Outputs that are inputs or
+    /// repeated get a buffer named `out_<position>`.
+    pub fn to_blif(&self) -> Result<String> {
+        let order = self.topological_order()?;
+        let mut names: HashMap<u32, String> = HashMap::new();
+        let mut inputs = Vec::new();
+        for (party, wires) in [("alice", &self.alice), ("bob", &self.bob)] {
+            for &wire in wires.iter().flatten() {
+                names.insert(wire, format!("{party}_{wire}"));
+                inputs.push(names[&wire].clone());
+            }
+        }
+
+        let mut body = String::new();
+        let mut constants: Vec<(&u32, &u8)> = self.constants.iter().collect();
+        constants.sort();
+        for (&wire, &value) in constants {
+            let name = format!("w{wire}");
+            body += &names_block(&[], &name, &[value]);
+            names.insert(wire, name);
+        }
+        for gate in order {
+            let name = format!("w{}", gate.id);
+            let inputs: Vec<&str> = gate
+                .inputs
+                .iter()
+                .map(|wire| names[wire].as_str())
+                .collect();
+            body += &names_block(&inputs, &name, &gate.truth_table());
+            names.insert(gate.id, name);
+        }
+
+        let mut outputs: Vec<String> = Vec::new();
+        for (position, wire) in self.out.iter().enumerate() {
+            let name = &names[wire];
+            if self.input_wires().all(|input| input != *wire) && !outputs.contains(name) {
+                outputs.push(name.clone());
+            } else {
+                let output = format!("out_{position}");
+                body += &names_block(&[name.as_str()], &output, &[0, 1]);
+                outputs.push(output);
+            }
+        }
+
+        let model: String = self
+            .id
+            .chars()
+            .map(|c| if c.is_whitespace() { '_' } else { c })
+            .collect();
+        Ok(format!(
+            ".model {model}\n.inputs {}\n.outputs {}\n{body}.end\n",
+            inputs.join(" "),
+            outputs.join(" ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{assert_equivalent, evaluate, round_trip_circuits};
+
+    #[test]
+    fn test_blif_round_trip() {
+        for circuit in round_trip_circuits() {
+            let text = circuit.to_blif().unwrap();
+            let parsed = Circuit::from_blif(&text, &PortAssignment::default()).unwrap();
+            assert_equivalent(&circuit, &parsed);
+            assert_eq!(
+                parsed.alice.iter().flatten().count(),
+                circuit.alice.iter().flatten().count()
+            );
+
+            // Gates of up to two inputs come back as fixed gate types
+            assert!(parsed
+                .gates
+                .iter()
+                .all(|gate| gate.inputs.len() > 2 || gate.gate_type != GateType::Lut));
+        }
+    }
+
+    #[test]
+    fn test_parse_blif() {
+        // A full adder, with an off-set cover, a continued line and comments
+        let text = "\
+# full adder
+.model full_adder
+.inputs a b \\
+  cin
+.outputs sum cout
+.names a b t
+01 1
+10 1
+.names t cin sum
+00 0  # off-set
+11 0
+.names a b cin cout
+11- 1
+1-1 1
+-11 1
+.end
+.model ignored
+";
+        let ports: PortAssignment =
+            serde_json::from_str(r#"{"alice": ["a"], "bob": ["b", "cin"]}"#).unwrap();
+        let circuit = Circuit::from_blif(text, &ports).unwrap();
+        assert_eq!(circuit.id, "full_adder");
+        assert_eq!(circuit.gates[0].gate_type, GateType::Xor);
+        assert_eq!(circuit.gates[1].gate_type, GateType::Xor);
+        assert_eq!(circuit.gates[2].gate_type, GateType::Lut);
+        for inputs in 0..8u8 {
+            let bits = [inputs & 1, (inputs >> 1) & 1, inputs >> 2];
+            let total = bits.iter().sum::<u8>();
+            assert_eq!(evaluate(&circuit, &bits), vec![total & 1, total >> 1]);
+        }
+    }
+
+    #[test]
+    fn test_blif_errors() {
+        let ports = PortAssignment::default();
+        let latch = ".model m\n.inputs alice\n.outputs q\n.latch alice q re clk 0\n.end\n";
+        assert!(matches!(
+            Circuit::from_blif(latch, &ports),
+            Err(Error::Import(_))
+        ));
+        let mixed = ".model m\n.inputs alice bob\n.outputs y\n.names alice bob y\n11 1\n00 0\n";
+        assert!(matches!(
+            Circuit::from_blif(mixed, &ports),
+            Err(Error::Syntax { line: 6, .. })
+        ));
+        let short = ".model m\n.inputs alice bob\n.outputs y\n.names alice bob y\n1 1\n";
+        assert!(matches!(
+            Circuit::from_blif(short, &ports),
+            Err(Error::Syntax { line: 5, .. })
+        ));
+        let output = ".model m\n.inputs alice bob\n.outputs y\n.names alice bob y\n11 2\n";
+        assert!(matches!(
+            Circuit::from_blif(output, &ports),
+            Err(Error::Syntax { line: 5, .. })
+        ));
+    }
+}
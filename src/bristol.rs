@@ -0,0 +1,494 @@
+use crate::circuit::{Circuit, Gate, GateType};
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Tokens of every non-empty line, with 1-based line numbers
+fn token_lines(text: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.split_whitespace().collect::<Vec<_>>()))
+        .filter(|(_, tokens)| !tokens.is_empty())
+}
+
+fn parse_number<T: FromStr>(line: usize, token: &str) -> Result<T> {
+    token
+        .parse()
+        .map_err(|_| Error::syntax(line, format!("expected a number, found `{token}`")))
+}
+
+/// Widths of the groups on a header line: a count followed by one width each
+fn parse_groups(line: usize, tokens: &[&str]) -> Result<Vec<u32>> {
+    let widths = tokens[1..]
+        .iter()
+        .map(|token| parse_number(line, token))
+        .collect::<Result<Vec<u32>>>()?;
+    if parse_number::<usize>(line, tokens[0])? != widths.len() {
+        return Err(Error::syntax(line, "group count does not match the widths"));
+    }
+    Ok(widths)
+}
+
+/// Gate of a Bristol Fashion file being written, over provisional wire
+/// numbers; the input of an `EQ` gate is the constant itself
+struct BristolGate {
+    op: &'static str,
+    inputs: Vec<usize>,
+    output: usize,
+}
+
+/// Bristol Fashion gates in the order they are emitted
+#[derive(Default)]
+struct BristolWriter {
+    gates: Vec<BristolGate>,
+    wire_count: usize,
+}
+
+impl BristolWriter {
+    fn push(&mut self, op: &'static str, inputs: Vec<usize>) -> usize {
+        let output = self.wire_count;
+        self.wire_count += 1;
+        self.gates.push(BristolGate { op, inputs, output });
+        output
+    }
+
+    /// Emit a gate as the XOR of the AND monomials of its algebraic normal
+    /// form, so any gate type is written with AND, XOR, INV, EQ and EQW only
+    fn push_gate(&mut self, gate: &Gate, inputs: &[usize]) -> usize {
+        let first_wire = self.wire_count;
+        let anf = algebraic_normal_form(gate);
+        let mut products = HashMap::new();
+        let terms: Vec<usize> = (1..anf.len())
+            .filter(|&mask| anf[mask] == 1)
+            .map(|mask| self.product(mask, inputs, &mut products))
+            .collect();
+
+        let Some((&first, rest)) = terms.split_first() else {
+            return self.push("EQ", vec![anf[0] as usize]);
+        };
+        let mut output = first;
+        for &term in rest {
+            output = self.push("XOR", vec![output, term]);
+        }
+        if anf[0] == 1 {
+            self.push("INV", vec![output])
+        } else if output < first_wire {
+            // The gate passes an existing wire through, which needs a copy
+            self.push("EQW", vec![output])
+        } else {
+            output
+        }
+    }
+
+    /// AND of the inputs selected by `mask`, sharing products between the
+    /// monomials of one gate
+    fn product(
+        &mut self,
+        mask: usize,
+        inputs: &[usize],
+        products: &mut HashMap<usize, usize>,
+    ) -> usize {
+        if mask.count_ones() == 1 {
+            return inputs[mask.trailing_zeros() as usize];
+        }
+        if let Some(&wire) = products.get(&mask) {
+            return wire;
+        }
+        let highest = usize::BITS - 1 - mask.leading_zeros();
+        let rest = self.product(mask ^ (1 << highest), inputs, products);
+        let wire = self.push("AND", vec![rest, inputs[highest as usize]]);
+        products.insert(mask, wire);
+        wire
+    }
+}
+
+/// Coefficients of the algebraic normal form of a gate, indexed by the mask
+/// of inputs in each monomial with bit `j` standing for input `j`
+fn algebraic_normal_form(gate: &Gate) -> Vec<u8> {
+    let arity = gate.inputs.len();
+    let mut coefficients: Vec<u8> = (0..1usize << arity)
+        .map(|mask| {
+            let bits: Vec<u8> = (0..arity).map(|j| ((mask >> j) & 1) as u8).collect();
+            gate.eval(&bits)
+        })
+        .collect();
+    for j in 0..arity {
+        for mask in 0..coefficients.len() {
+            if mask & (1 << j) != 0 {
+                coefficients[mask] ^= coefficients[mask ^ (1 << j)];
+            }
+        }
+    }
+    coefficients
+}
+
+impl Circuit {
+    /// Parse a circuit in Bristol Fashion or in the legacy Bristol format
+    ///
+    /// Input wires are numbered first in group order and the outputs are the
+    /// last wires of the circuit. The first input group becomes Alice's
+    /// inputs and all other groups Bob's; the output groups are concatenated.
+    /// Wire numbers are kept as wire ids.
+    ///
+    /// The legacy format has a single header line with the input counts of
+    /// the two parties and the output count, and is told apart from Bristol
+    /// Fashion by a gate following that line.
+    pub fn from_bristol(id: &str, text: &str) -> Result<Circuit> {
+        let mut lines = token_lines(text);
+        let mut header = |name: &str| {
+            lines
+                .next()
+                .ok_or_else(|| Error::syntax(0, format!("missing {name} line")))
+        };
+
+        let (line, counts) = header("gate and wire count")?;
+        let [gate_count, wire_count] = counts[..] else {
+            return Err(Error::syntax(line, "expected a gate and a wire count"));
+        };
+        let gate_count: usize = parse_number(line, gate_count)?;
+        let wire_count: u32 = parse_number(line, wire_count)?;
+        let (input_line, inputs) = header("input groups")?;
+        let (line, tokens) = header("output groups")?;
+        let is_fashion = tokens.last().unwrap().parse::<u32>().is_ok();
+        let (input_widths, output_widths, first_gate) = if is_fashion {
+            let input_widths = parse_groups(input_line, &inputs)?;
+            (input_widths, parse_groups(line, &tokens)?, None)
+        } else {
+            let [alice, bob, outputs] = inputs[..] else {
+                return Err(Error::syntax(
+                    input_line,
+                    "expected two input counts and an output count",
+                ));
+            };
+            let input_widths = vec![
+                parse_number(input_line, alice)?,
+                parse_number(input_line, bob)?,
+            ];
+            let output_widths = vec![parse_number(input_line, outputs)?];
+            (input_widths, output_widths, Some((line, tokens)))
+        };
+
+        // The counts come from the file, so their sums may not fit a wire id
+        let too_many = || Error::syntax(input_line, "more inputs and outputs than wires");
+        let total = |widths: &[u32]| {
+            widths
+                .iter()
+                .try_fold(0u32, |total, &width| total.checked_add(width))
+                .ok_or_else(too_many)
+        };
+        let input_count = total(&input_widths)?;
+        let output_count = total(&output_widths)?;
+        if input_count
+            .checked_add(output_count)
+            .is_none_or(|count| count > wire_count)
+        {
+            return Err(too_many());
+        }
+
+        let mut gates = Vec::new();
+        let mut gate_lines = 0;
+        for (line, tokens) in first_gate.into_iter().chain(lines) {
+            gate_lines += 1;
+            let (&op, numbers) = tokens.split_last().unwrap();
+            let numbers = numbers
+                .iter()
+                .map(|token| parse_number(line, token))
+                .collect::<Result<Vec<u32>>>()?;
+            let (input_len, output_len) = match numbers[..] {
+                [inputs, outputs, ..] => (inputs as usize, outputs as usize),
+                _ => return Err(Error::syntax(line, "expected input and output counts")),
+            };
+            let wires = &numbers[2..];
+            if wires.len() != input_len + output_len {
+                return Err(Error::syntax(line, "wire count does not match the gate"));
+            }
+            // The input of EQ is a constant rather than a wire
+            let checked = if op == "EQ" { input_len } else { 0 };
+            if let Some(wire) = wires[checked..].iter().find(|&&wire| wire >= wire_count) {
+                return Err(Error::syntax(line, format!("wire {wire} is out of range")));
+            }
+
+            let (inputs, outputs) = wires.split_at(input_len);
+            match (op, input_len, output_len) {
+                ("AND", 2, 1) => gates.push(Gate::new(outputs[0], GateType::And, inputs.to_vec())),
+                ("XOR", 2, 1) => gates.push(Gate::new(outputs[0], GateType::Xor, inputs.to_vec())),
+                ("INV" | "NOT", 1, 1) => {
+                    gates.push(Gate::new(outputs[0], GateType::Not, inputs.to_vec()))
+                }
+                ("EQW", 1, 1) => gates.push(Gate::new(outputs[0], GateType::Buf, inputs.to_vec())),
+                ("EQ", 1, 1) => {
+                    let gate_type = match inputs[0] {
+                        0 => GateType::Zero,
+                        1 => GateType::One,
+                        value => return Err(Error::syntax(line, format!("EQ of non-bit {value}"))),
+                    };
+                    gates.push(Gate::new(outputs[0], gate_type, Vec::new()));
+                }
+                ("MAND", _, count) if input_len == 2 * count => {
+                    for (i, &output) in outputs.iter().enumerate() {
+                        let inputs = vec![inputs[i], inputs[count + i]];
+                        gates.push(Gate::new(output, GateType::And, inputs));
+                    }
+                }
+                _ => {
+                    return Err(Error::syntax(
+                        line,
+                        format!(
+                        "unsupported gate {op} with {input_len} inputs and {output_len} outputs"
+                    ),
+                    ))
+                }
+            }
+        }
+        if gate_lines != gate_count {
+            return Err(Error::syntax(
+                0,
+                format!("expected {gate_count} gates, found {gate_lines}"),
+            ));
+        }
+
+        let mut groups = input_widths.iter().scan(0, |start, &width| {
+            let wires: Vec<u32> = (*start..*start + width).collect();
+            *start += width;
+            Some(wires)
+        });
+        let alice = groups.next().filter(|wires| !wires.is_empty());
+        let bob: Vec<u32> = groups.flatten().collect();
+        let circuit = Circuit {
+            id: id.to_string(),
+            gates,
+            alice,
+            bob: (!bob.is_empty()).then_some(bob),
+            out: (wire_count - output_count..wire_count).collect(),
+            constants: HashMap::new(),
+        };
+        circuit.validate()?;
+        Ok(circuit)
+    }
+
+    /// Load a Bristol Fashion or legacy Bristol file, named after the file stem
+    pub fn load_bristol<P: AsRef<Path>>(path: P) -> Result<Circuit> {
+        let path = path.as_ref();
+        let id = path.file_stem().unwrap_or_default().to_string_lossy();
+        Circuit::from_bristol(&id, &fs::read_to_string(path)?)
+    }
+
+    /// Write the circuit in Bristol Fashion
+    ///
+    /// Alice's inputs form the first input group and Bob's the second, and
+    /// all outputs form one output group. Gate types Bristol Fashion lacks are
+    /// rewritten with AND, XOR and INV, constants become `EQ` gates, and
+    /// wires are renumbered.
+    pub fn to_bristol(&self) -> Result<String> {
+        let order = self.topological_order()?;
+        let alice = self.alice.clone().unwrap_or_default();
+        let bob = self.bob.clone().unwrap_or_default();
+
+        let mut writer = BristolWriter::default();
+        let mut wires: HashMap<u32, usize> = HashMap::new();
+        for &wire in alice.iter().chain(&bob) {
+            wires.insert(wire, writer.wire_count);
+            writer.wire_count += 1;
+        }
+        let input_count = writer.wire_count;
+
+        let mut constants: Vec<(&u32, &u8)> = self.constants.iter().collect();
+        constants.sort();
+        for (&wire, &value) in constants {
+            wires.insert(wire, writer.push("EQ", vec![value as usize]));
+        }
+        for gate in order {
+            let inputs: Vec<usize> = gate.inputs.iter().map(|wire| wires[wire]).collect();
+            let output = writer.push_gate(gate, &inputs);
+            wires.insert(gate.id, output);
+        }
+
+        // Outputs must be distinct wires numbered last, so inputs and repeated
+        // outputs are copied
+        let mut outputs: Vec<usize> = Vec::new();
+        for wire in &self.out {
+            let mut output = wires[wire];
+            if output < input_count || outputs.contains(&output) {
+                output = writer.push("EQW", vec![output]);
+            }
+            outputs.push(output);
+        }
+
+        let mut numbers = vec![None; writer.wire_count];
+        let first_output = writer.wire_count - outputs.len();
+        for (position, &output) in outputs.iter().enumerate() {
+            numbers[output] = Some(first_output + position);
+        }
+        let unnumbered = numbers.iter_mut().filter(|number| number.is_none());
+        for (next, number) in unnumbered.enumerate() {
+            *number = Some(next);
+        }
+        let number = |wire: usize| numbers[wire].unwrap();
+
+        let mut input_widths = vec![alice.len()];
+        if self.bob.is_some() {
+            input_widths.push(bob.len());
+        }
+        let join = |values: Vec<usize>| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut lines = vec![
+            format!("{} {}", writer.gates.len(), writer.wire_count),
+            format!("{} {}", input_widths.len(), join(input_widths)),
+            format!("1 {}", outputs.len()),
+            String::new(),
+        ];
+        for gate in &writer.gates {
+            let inputs = match gate.op {
+                "EQ" => gate.inputs.clone(),
+                _ => gate.inputs.iter().map(|&wire| number(wire)).collect(),
+            };
+            lines.push(format!(
+                "{} 1 {} {} {}",
+                inputs.len(),
+                join(inputs),
+                number(gate.output),
+                gate.op
+            ));
+        }
+        Ok(lines.join("\n") + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::CircuitError;
+    use crate::test_support::{assert_equivalent, evaluate, round_trip_circuits};
+
+    // A 2-bit adder with a constant carry-in of 1, sum bits on wires 10 and 11
+    const ADDER: &str = "\
+7 12
+2 2 2
+1 2
+
+1 1 1 4 EQ
+2 1 0 2 5 XOR
+2 1 5 4 10 XOR
+4 2 0 5 2 4 6 7 MAND
+2 1 6 7 8 XOR
+2 1 1 3 9 XOR
+2 1 9 8 11 XOR
+";
+
+    #[test]
+    fn test_parse_bristol_fashion() {
+        let circuit = Circuit::from_bristol("adder", ADDER).unwrap();
+        assert_eq!(circuit.alice, Some(vec![0, 1]));
+        assert_eq!(circuit.bob, Some(vec![2, 3]));
+        assert_eq!(circuit.out, vec![10, 11]);
+
+        // MAND expands into one AND gate per output
+        assert_eq!(circuit.gates.len(), 8);
+        assert_eq!(circuit.gates[0].gate_type, GateType::One);
+        let and_gates: Vec<(u32, GateType, Vec<u32>)> = circuit.gates[3..5]
+            .iter()
+            .map(|gate| (gate.id, gate.gate_type, gate.inputs.clone()))
+            .collect();
+        assert_eq!(
+            and_gates,
+            vec![
+                (6, GateType::And, vec![0, 2]),
+                (7, GateType::And, vec![5, 4])
+            ]
+        );
+
+        for a in 0..4u8 {
+            for b in 0..4u8 {
+                let sum = (a + b + 1) & 3;
+                let bits = [a & 1, a >> 1, b & 1, b >> 1];
+                assert_eq!(
+                    evaluate(&circuit, &bits),
+                    vec![sum & 1, sum >> 1],
+                    "{a} + {b} + 1"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bristol_round_trip() {
+        for circuit in round_trip_circuits() {
+            let text = circuit.to_bristol().unwrap();
+            let parsed = Circuit::from_bristol(&circuit.id, &text).unwrap();
+            assert_eq!(parsed.out.len(), circuit.out.len());
+            assert_equivalent(&circuit, &parsed);
+
+            // Only gates Bristol Fashion defines are written
+            assert!(parsed.gates.iter().all(|gate| matches!(
+                gate.gate_type,
+                GateType::And
+                    | GateType::Xor
+                    | GateType::Not
+                    | GateType::Buf
+                    | GateType::Zero
+                    | GateType::One
+            )));
+        }
+    }
+
+    #[test]
+    fn test_parse_legacy_bristol() {
+        // The adder of circuits/adder2.txt, with MAND split into AND gates
+        let text = "\
+7 11
+2 2 3
+
+2 1 0 2 8 XOR
+2 1 0 2 4 AND
+2 1 1 3 5 XOR
+2 1 5 4 9 XOR
+2 1 1 3 6 AND
+2 1 5 4 7 AND
+2 1 6 7 10 XOR
+";
+        let circuit = Circuit::from_bristol("adder2", text).unwrap();
+        assert_eq!(circuit.alice, Some(vec![0, 1]));
+        assert_eq!(circuit.bob, Some(vec![2, 3]));
+        assert_eq!(circuit.out, vec![8, 9, 10]);
+        assert_eq!(circuit.gates.len(), 7);
+
+        let fashion = Circuit::load_bristol("circuits/adder2.txt").unwrap();
+        assert_equivalent(&fashion, &circuit);
+    }
+
+    #[test]
+    fn test_bristol_errors() {
+        let syntax_line = |text: &str| match Circuit::from_bristol("bad", text) {
+            Err(Error::Syntax { line, .. }) => line,
+            other => panic!("expected a syntax error, got {other:?}"),
+        };
+        assert_eq!(syntax_line("1 3\n2 1 1\n1 1\n\n2 1 0 1 2 OR\n"), 5);
+        assert_eq!(syntax_line("1 3\n2 1 1\n1 1\n\n2 1 0 1 3 AND\n"), 5);
+        assert_eq!(syntax_line("1 3\n3 1 1\n1 1\n"), 2);
+        assert_eq!(syntax_line("1 3\n2 1 1\n1 1\n\n2 1 0 x 2 AND\n"), 5);
+        assert_eq!(syntax_line("2 3\n2 1 1\n1 1\n\n2 1 0 1 2 AND\n"), 0);
+        assert_eq!(syntax_line("1 3\n2 1\n\n2 1 0 1 2 AND\n"), 2);
+        assert_eq!(syntax_line("1 4\n1 1 1\n\n2 1 0 1 3 OR\n"), 4);
+        // Header counts whose sums overflow
+        assert_eq!(
+            syntax_line("1 3\n2 4294967295 1\n1 1\n\n2 1 0 1 2 AND\n"),
+            2
+        );
+        assert_eq!(syntax_line("1 3\n1 4294967295\n1 1\n\n2 1 0 1 2 AND\n"), 2);
+
+        // Well-formed files must still describe a valid circuit
+        let result = Circuit::from_bristol("bad", "1 3\n2 1 1\n1 1\n\n2 1 0 2 2 AND\n");
+        assert!(matches!(
+            result,
+            Err(Error::MalformedCircuit(CircuitError::Cycle(2)))
+        ));
+    }
+}
@@ -0,0 +1,470 @@
+use crate::circuit::{Circuit, Gate, GateType};
+use crate::error::Result;
+use crate::gadgets::{self, Variant};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Rem, Shl, Shr, Sub};
+use std::rc::Rc;
+
+/// Wires and gates recorded so far
+#[derive(Default)]
+struct Recording {
+    wire_count: u32,
+    gates: Vec<Gate>,
+    alice: Vec<u32>,
+    bob: Vec<u32>,
+    out: Vec<u32>,
+    constants: HashMap<u32, u8>,
+}
+
+impl Recording {
+    fn wire(&mut self) -> u32 {
+        self.wire_count += 1;
+        self.wire_count - 1
+    }
+}
+
+/// Records a circuit from operations on `Bit` and `UInt` handles
+///
+/// Wires are numbered from 0 in the order they are made. Handles keep a
+/// reference to the builder that made them, so that their operators can
+/// record gates; combining handles of different builders panics.
+#[derive(Clone, Default)]
+pub struct CircuitBuilder {
+    recording: Rc<RefCell<Recording>>,
+}
+
+impl CircuitBuilder {
+    pub fn new() -> Self {
+        CircuitBuilder::default()
+    }
+
+    fn bit(&self, wire: u32) -> Bit {
+        Bit {
+            wire,
+            recording: Rc::clone(&self.recording),
+        }
+    }
+
+    /// New input bit provided by Alice
+    pub fn alice_bit(&self) -> Bit {
+        let mut recording = self.recording.borrow_mut();
+        let wire = recording.wire();
+        recording.alice.push(wire);
+        self.bit(wire)
+    }
+
+    /// New input bit provided by Bob
+    pub fn bob_bit(&self) -> Bit {
+        let mut recording = self.recording.borrow_mut();
+        let wire = recording.wire();
+        recording.bob.push(wire);
+        self.bit(wire)
+    }
+
+    /// New `N`-bit input provided by Alice, least significant bit first
+    pub fn alice_uint<const N: usize>(&self) -> UInt<N> {
+        UInt::from_bits(std::array::from_fn(|_| self.alice_bit()))
+    }
+
+    /// New `N`-bit input provided by Bob, least significant bit first
+    pub fn bob_uint<const N: usize>(&self) -> UInt<N> {
+        UInt::from_bits(std::array::from_fn(|_| self.bob_bit()))
+    }
+
+    /// Constant wire holding `value`
+    pub fn constant(&self, value: bool) -> Bit {
+        let mut recording = self.recording.borrow_mut();
+        let wire = recording.wire();
+        recording.constants.insert(wire, value as u8);
+        self.bit(wire)
+    }
+
+    /// `N`-bit constant holding the low bits of `value`
+    pub fn constant_uint<const N: usize>(&self, value: u64) -> UInt<N> {
+        UInt::from_bits(std::array::from_fn(|i| {
+            self.constant(i < 64 && (value >> i) & 1 == 1)
+        }))
+    }
+
+    /// Append a bit to the circuit outputs
+    pub fn output(&self, bit: &Bit) {
+        assert!(
+            Rc::ptr_eq(&self.recording, &bit.recording),
+            "bit of another builder"
+        );
+        self.recording.borrow_mut().out.push(bit.wire);
+    }
+
+    /// Append the bits of an integer to the circuit outputs, least
+    /// significant bit first
+    pub fn output_uint<const N: usize>(&self, value: &UInt<N>) {
+        for bit in &value.bits {
+            self.output(bit);
+        }
+    }
+
+    /// Circuit of everything recorded so far, checked by `Circuit::validate`
+    pub fn build(&self, id: &str) -> Result<Circuit> {
+        let recording = self.recording.borrow();
+        let party = |wires: &Vec<u32>| (!wires.is_empty()).then(|| wires.clone());
+        let circuit = Circuit {
+            id: id.to_string(),
+            gates: recording.gates.clone(),
+            alice: party(&recording.alice),
+            bob: party(&recording.bob),
+            out: recording.out.clone(),
+            constants: recording.constants.clone(),
+        };
+        circuit.validate()?;
+        Ok(circuit)
+    }
+}
+
+/// Handle to a wire of a circuit being built
+#[derive(Clone)]
+pub struct Bit {
+    wire: u32,
+    recording: Rc<RefCell<Recording>>,
+}
+
+impl Bit {
+    /// Wire id of the bit in the built circuit
+    pub fn wire(&self) -> u32 {
+        self.wire
+    }
+
+    /// Builder recording the circuit of this bit
+    pub fn builder(&self) -> CircuitBuilder {
+        CircuitBuilder {
+            recording: Rc::clone(&self.recording),
+        }
+    }
+
+    /// `if_true` when this bit is 1 and `if_false` otherwise, with a single
+    /// AND gate
+    pub fn mux(&self, if_true: &Bit, if_false: &Bit) -> Bit {
+        if_false ^ &(self & &(if_true ^ if_false))
+    }
+}
+
+/// Record a gate over the inputs and return its output
+fn record(gate_type: GateType, inputs: &[&Bit]) -> Bit {
+    let recording = &inputs[0].recording;
+    assert!(
+        inputs
+            .iter()
+            .all(|input| Rc::ptr_eq(recording, &input.recording)),
+        "bits of different builders"
+    );
+    let wire = {
+        let mut recording = recording.borrow_mut();
+        let wire = recording.wire();
+        let wires = inputs.iter().map(|input| input.wire).collect();
+        recording.gates.push(Gate::new(wire, gate_type, wires));
+        wire
+    };
+    Bit {
+        wire,
+        recording: Rc::clone(recording),
+    }
+}
+
+macro_rules! bit_operator {
+    ($trait:ident, $method:ident, $gate_type:expr) => {
+        impl $trait for &Bit {
+            type Output = Bit;
+
+            fn $method(self, other: &Bit) -> Bit {
+                record($gate_type, &[self, other])
+            }
+        }
+
+        impl $trait for Bit {
+            type Output = Bit;
+
+            fn $method(self, other: Bit) -> Bit {
+                record($gate_type, &[&self, &other])
+            }
+        }
+    };
+}
+
+bit_operator!(BitAnd, bitand, GateType::And);
+bit_operator!(BitOr, bitor, GateType::Or);
+bit_operator!(BitXor, bitxor, GateType::Xor);
+
+impl Not for &Bit {
+    type Output = Bit;
+
+    fn not(self) -> Bit {
+        record(GateType::Not, &[self])
+    }
+}
+
+impl Not for Bit {
+    type Output = Bit;
+
+    fn not(self) -> Bit {
+        !&self
+    }
+}
+
+/// Unsigned `N`-bit integer of a circuit being built, least significant bit
+/// first
+///
+/// Arithmetic wraps around modulo `2^N` and uses the AND-optimized gadgets of
+/// `gadgets`. Division by zero gives unspecified bits.
+#[derive(Clone)]
+pub struct UInt<const N: usize> {
+    bits: [Bit; N],
+}
+
+impl<const N: usize> UInt<N> {
+    pub fn from_bits(bits: [Bit; N]) -> Self {
+        UInt { bits }
+    }
+
+    pub fn bits(&self) -> &[Bit; N] {
+        &self.bits
+    }
+
+    /// Integer of the bits a gadget returned, which are `N` wide
+    fn from_vec(bits: Vec<Bit>) -> Self {
+        match bits.try_into() {
+            Ok(bits) => UInt { bits },
+            Err(_) => unreachable!("gadget returned a different width"),
+        }
+    }
+
+    /// Bit that is 1 when both integers are equal
+    ///
+    /// Panics if `N` is 0.
+    pub fn equals(&self, other: &UInt<N>) -> Bit {
+        gadgets::equal(&self.bits, &other.bits)
+    }
+
+    /// Bit that is 1 when this integer is less than `other`
+    ///
+    /// Panics if `N` is 0.
+    pub fn less_than(&self, other: &UInt<N>) -> Bit {
+        gadgets::less_than(&self.bits, &other.bits, Variant::AndOptimized)
+    }
+
+    /// Bit that is 1 when this integer is at most `other`
+    ///
+    /// Panics if `N` is 0.
+    pub fn less_equal(&self, other: &UInt<N>) -> Bit {
+        gadgets::less_equal(&self.bits, &other.bits, Variant::AndOptimized)
+    }
+
+    /// `if_true` when `condition` is 1 and `if_false` otherwise
+    pub fn select(condition: &Bit, if_true: &UInt<N>, if_false: &UInt<N>) -> UInt<N> {
+        UInt::from_vec(gadgets::select(
+            condition,
+            &if_true.bits,
+            &if_false.bits,
+            Variant::AndOptimized,
+        ))
+    }
+
+    /// The larger of both integers
+    pub fn max(&self, other: &UInt<N>) -> UInt<N> {
+        UInt::select(&self.less_than(other), other, self)
+    }
+
+    /// The smaller of both integers
+    pub fn min(&self, other: &UInt<N>) -> UInt<N> {
+        UInt::select(&self.less_than(other), self, other)
+    }
+
+    fn bitwise(&self, other: &UInt<N>, operation: impl Fn(&Bit, &Bit) -> Bit) -> UInt<N> {
+        UInt::from_bits(std::array::from_fn(|i| {
+            operation(&self.bits[i], &other.bits[i])
+        }))
+    }
+}
+
+macro_rules! uint_operator {
+    ($trait:ident, $method:ident, |$a:ident, $b:ident| $body:expr) => {
+        impl<const N: usize> $trait for &UInt<N> {
+            type Output = UInt<N>;
+
+            fn $method(self, other: &UInt<N>) -> UInt<N> {
+                let ($a, $b) = (self, other);
+                $body
+            }
+        }
+
+        impl<const N: usize> $trait for UInt<N> {
+            type Output = UInt<N>;
+
+            fn $method(self, other: UInt<N>) -> UInt<N> {
+                let ($a, $b) = (&self, &other);
+                $body
+            }
+        }
+    };
+}
+
+uint_operator!(BitAnd, bitand, |a, b| a.bitwise(b, |a, b| a & b));
+uint_operator!(BitOr, bitor, |a, b| a.bitwise(b, |a, b| a | b));
+uint_operator!(BitXor, bitxor, |a, b| a.bitwise(b, |a, b| a ^ b));
+uint_operator!(Add, add, |a, b| UInt::from_vec(gadgets::add_wrapping(
+    &a.bits,
+    &b.bits,
+    Variant::AndOptimized
+)));
+uint_operator!(Sub, sub, |a, b| UInt::from_vec(gadgets::subtract_wrapping(
+    &a.bits,
+    &b.bits,
+    Variant::AndOptimized
+)));
+uint_operator!(Mul, mul, |a, b| UInt::from_vec(gadgets::multiply_wrapping(
+    &a.bits,
+    &b.bits,
+    Variant::AndOptimized
+)));
+uint_operator!(Div, div, |a, b| UInt::from_vec(
+    gadgets::divide(&a.bits, &b.bits, Variant::AndOptimized).0
+));
+uint_operator!(Rem, rem, |a, b| UInt::from_vec(gadgets::reduce(
+    &a.bits,
+    &b.bits,
+    Variant::AndOptimized
+)));
+
+impl<const N: usize> Shl<usize> for &UInt<N> {
+    type Output = UInt<N>;
+
+    fn shl(self, shift: usize) -> UInt<N> {
+        UInt::from_vec(gadgets::shift_left(&self.bits, shift))
+    }
+}
+
+impl<const N: usize> Shr<usize> for &UInt<N> {
+    type Output = UInt<N>;
+
+    fn shr(self, shift: usize) -> UInt<N> {
+        UInt::from_vec(gadgets::shift_right(&self.bits, shift))
+    }
+}
+
+impl<const N: usize> Not for &UInt<N> {
+    type Output = UInt<N>;
+
+    fn not(self) -> UInt<N> {
+        UInt::from_bits(std::array::from_fn(|i| !&self.bits[i]))
+    }
+}
+
+impl<const N: usize> Not for UInt<N> {
+    type Output = UInt<N>;
+
+    fn not(self) -> UInt<N> {
+        !&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::evaluate;
+
+    /// Bits of `value`, least significant first
+    fn bits(value: u8, width: usize) -> Vec<u8> {
+        (0..width).map(|i| (value >> i) & 1).collect()
+    }
+
+    #[test]
+    fn test_bit_operators() {
+        let builder = CircuitBuilder::new();
+        let a = builder.alice_bit();
+        let b = builder.bob_bit();
+        let c = builder.bob_bit();
+        builder.output(&(&a & &b));
+        builder.output(&(&a | &b));
+        builder.output(&(a.clone() ^ b.clone()));
+        builder.output(&!&a);
+        builder.output(&c.mux(&a, &b));
+        let circuit = builder.build("bits").unwrap();
+        assert_eq!(circuit.alice, Some(vec![0]));
+        assert_eq!(circuit.bob, Some(vec![1, 2]));
+
+        for inputs in 0..8u8 {
+            let [a, b, c] = [inputs & 1, (inputs >> 1) & 1, inputs >> 2];
+            let mux = if c == 1 { a } else { b };
+            assert_eq!(
+                evaluate(&circuit, &[a, b, c]),
+                vec![a & b, a | b, a ^ b, 1 - a, mux]
+            );
+        }
+    }
+
+    #[test]
+    fn test_uint_operators() {
+        let builder = CircuitBuilder::new();
+        let a = builder.alice_uint::<3>();
+        let b = builder.bob_uint::<3>();
+        let one = builder.constant_uint::<3>(1);
+        builder.output_uint(&(&a + &b));
+        builder.output_uint(&(&a - &b));
+        builder.output_uint(&(&(&a & &b) | &(!&a ^ one)));
+        builder.output(&a.equals(&b));
+        builder.output(&a.less_than(&b));
+        builder.output_uint(&a.max(&b));
+        builder.output_uint(&a.min(&b));
+        builder.output_uint(&(&a * &b));
+        builder.output_uint(&(&(&a << 1) ^ &(&b >> 2)));
+        builder.output(&a.less_equal(&b));
+        let circuit = builder.build("uint").unwrap();
+
+        let builder = CircuitBuilder::new();
+        let (x, y) = (builder.alice_uint::<3>(), builder.bob_uint::<3>());
+        builder.output_uint(&(&x / &y));
+        builder.output_uint(&(&x % &y));
+        let division = builder.build("division").unwrap();
+
+        // Adding and subtracting take one AND gate per carried bit
+        let and_count = |id: &str| {
+            let builder = CircuitBuilder::new();
+            let (a, b) = (builder.alice_uint::<8>(), builder.bob_uint::<8>());
+            builder.output_uint(&if id == "add" { &a + &b } else { &a - &b });
+            let circuit = builder.build(id).unwrap();
+            circuit
+                .gates
+                .iter()
+                .filter(|gate| gate.gate_type == GateType::And)
+                .count()
+        };
+        assert_eq!((and_count("add"), and_count("sub")), (7, 7));
+
+        for a in 0..8u8 {
+            for b in 0..8u8 {
+                let mut expected = bits(a.wrapping_add(b) & 7, 3);
+                expected.extend(bits(a.wrapping_sub(b) & 7, 3));
+                expected.extend(bits((a & b) | ((!a ^ 1) & 7), 3));
+                expected.extend([(a == b) as u8, (a < b) as u8]);
+                expected.extend(bits(a.max(b), 3));
+                expected.extend(bits(a.min(b), 3));
+                expected.extend(bits(a.wrapping_mul(b) & 7, 3));
+                expected.extend(bits(((a << 1) ^ (b >> 2)) & 7, 3));
+                expected.push((a <= b) as u8);
+                let inputs = [bits(a, 3), bits(b, 3)].concat();
+                assert_eq!(evaluate(&circuit, &inputs), expected, "{a} and {b}");
+                if let (Some(quotient), Some(remainder)) = (a.checked_div(b), a.checked_rem(b)) {
+                    let expected = [bits(quotient, 3), bits(remainder, 3)].concat();
+                    assert_eq!(evaluate(&division, &inputs), expected, "{a} / {b}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "bits of different builders")]
+    fn test_mixed_builders() {
+        let a = CircuitBuilder::new().alice_bit();
+        let b = CircuitBuilder::new().bob_bit();
+        let _ = a & b;
+    }
+}
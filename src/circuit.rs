@@ -1,14 +1,261 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::path::Path;
 
+/// Boolean function computed by a gate
+///
+/// Covers all sixteen functions of two inputs, with the two constant functions
+/// taking no inputs, plus the unary NOT and BUF and lookup tables over up to
+/// `MAX_LUT_INPUTS` inputs. Names are the upper-case variant names in circuit
+/// files.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum GateType {
+    #[serde(alias = "FALSE")]
+    Zero,
+    #[serde(alias = "TRUE")]
+    One,
+    And,
+    Nand,
+    Or,
+    Nor,
+    Xor,
+    Xnor,
+    /// `a & !b`
+    #[serde(alias = "ANDNOT")]
+    NImply,
+    /// `!a & b`
+    CNImply,
+    /// `!a | b`
+    Imply,
+    /// `a | !b`
+    #[serde(alias = "ORNOT")]
+    CImply,
+    /// `a`, ignoring `b`
+    ProjA,
+    /// `b`, ignoring `a`
+    ProjB,
+    /// `!a`, ignoring `b`
+    NotA,
+    /// `!b`, ignoring `a`
+    NotB,
+    #[serde(alias = "INV")]
+    Not,
+    #[serde(alias = "EQW")]
+    Buf,
+    /// Lookup table given by the gate's `table`
+    Lut,
+}
+
+/// Most inputs a LUT gate may have, so its table holds at most 256 rows
+pub const MAX_LUT_INPUTS: usize = 8;
+
+impl GateType {
+    /// Every gate type
+    pub const ALL: [GateType; 19] = [
+        GateType::Zero,
+        GateType::One,
+        GateType::And,
+        GateType::Nand,
+        GateType::Or,
+        GateType::Nor,
+        GateType::Xor,
+        GateType::Xnor,
+        GateType::NImply,
+        GateType::CNImply,
+        GateType::Imply,
+        GateType::CImply,
+        GateType::ProjA,
+        GateType::ProjB,
+        GateType::NotA,
+        GateType::NotB,
+        GateType::Not,
+        GateType::Buf,
+        GateType::Lut,
+    ];
+
+    /// Fixed-function gate type with the given arity and truth table, in the
+    /// layout of `truth_table`
+    pub fn from_truth_table(arity: usize, table: u8) -> Option<GateType> {
+        GateType::ALL.into_iter().find(|gate_type| {
+            gate_type.arity() == Some(arity) && gate_type.truth_table() == Some(table)
+        })
+    }
+
+    /// Number of inputs the gate takes, or `None` for a LUT whose table
+    /// decides it
+    pub fn arity(&self) -> Option<usize> {
+        match self {
+            GateType::Zero | GateType::One => Some(0),
+            GateType::Not | GateType::Buf => Some(1),
+            GateType::Lut => None,
+            _ => Some(2),
+        }
+    }
+
+    /// Truth table with the output for input bits `i` at bit `i`, the first
+    /// input being the most significant bit of `i`, or `None` for a LUT
+    pub fn truth_table(&self) -> Option<u8> {
+        let table = match self {
+            GateType::Zero => 0b0,
+            GateType::One => 0b1,
+            GateType::And => 0b1000,
+            GateType::Nand => 0b0111,
+            GateType::Or => 0b1110,
+            GateType::Nor => 0b0001,
+            GateType::Xor => 0b0110,
+            GateType::Xnor => 0b1001,
+            GateType::NImply => 0b0100,
+            GateType::CNImply => 0b0010,
+            GateType::Imply => 0b1011,
+            GateType::CImply => 0b1101,
+            GateType::ProjA => 0b1100,
+            GateType::ProjB => 0b1010,
+            GateType::NotA => 0b0011,
+            GateType::NotB => 0b0101,
+            GateType::Not => 0b01,
+            GateType::Buf => 0b10,
+            GateType::Lut => return None,
+        };
+        Some(table)
+    }
+
+    /// Output of a fixed-function gate on its input bits, or `None` for a LUT
+    pub fn eval(&self, bits: &[u8]) -> Option<u8> {
+        let index = bits
+            .iter()
+            .fold(0, |index, &bit| (index << 1) | bit as usize);
+        self.truth_table().map(|table| (table >> index) & 1)
+    }
+
+    /// Write a two-input gate as `((a ^ alpha) & (b ^ beta)) ^ gamma`, if it
+    /// has that form
+    ///
+    /// These are exactly the two-input functions with an odd number of ones
+    /// in their truth table.
+    pub fn and_form(&self) -> Option<(u8, u8, u8)> {
+        let table = self.truth_table()?;
+        if self.arity() != Some(2) || table.count_ones() & 1 == 0 {
+            return None;
+        }
+
+        // The odd row out is where both factors are 1
+        let gamma = u8::from(table.count_ones() == 3);
+        let odd_row = (0..4).find(|row| (table >> row) & 1 != gamma).unwrap();
+        Some((1 ^ (odd_row >> 1), 1 ^ (odd_row & 1), gamma))
+    }
+
+    /// Write a gate with inputs as the XOR of a subset of its inputs and a
+    /// constant, returning one coefficient per input and the constant
+    pub fn affine_form(&self) -> Option<(Vec<u8>, u8)> {
+        let arity = self.arity()?;
+        if arity == 0 {
+            return None;
+        }
+
+        let constant = self.eval(&vec![0; arity])?;
+        let coefficients: Vec<u8> = (0..arity)
+            .map(|position| {
+                let bits: Vec<u8> = (0..arity).map(|i| u8::from(i == position)).collect();
+                self.eval(&bits).unwrap() ^ constant
+            })
+            .collect();
+
+        // Every row must match the XOR of the coefficients it selects
+        let affine = (0..1usize << arity).all(|row| {
+            let bits: Vec<u8> = (0..arity)
+                .map(|i| ((row >> (arity - 1 - i)) & 1) as u8)
+                .collect();
+            let expected = bits
+                .iter()
+                .zip(&coefficients)
+                .fold(constant, |acc, (bit, coefficient)| {
+                    acc ^ (bit & coefficient)
+                });
+            self.eval(&bits) == Some(expected)
+        });
+        affine.then_some((coefficients, constant))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Gate {
     pub id: u32,
     #[serde(rename = "type")]
-    pub gate_type: String,
+    pub gate_type: GateType,
     #[serde(rename = "in")]
     pub inputs: Vec<u32>,
+    /// Output bit of a LUT gate for every input combination, the first input
+    /// being the most significant bit of the row index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<Vec<u8>>,
+}
+
+impl Gate {
+    pub fn new(id: u32, gate_type: GateType, inputs: Vec<u32>) -> Self {
+        Gate {
+            id,
+            gate_type,
+            inputs,
+            table: None,
+        }
+    }
+
+    /// LUT gate over `inputs` with one output bit per input combination
+    ///
+    /// Panics if the table does not have `2^k` entries for `k` inputs or if
+    /// there are more than `MAX_LUT_INPUTS` inputs.
+    pub fn lut(id: u32, inputs: Vec<u32>, table: Vec<u8>) -> Self {
+        assert!(
+            inputs.len() <= MAX_LUT_INPUTS,
+            "LUT gate {id} has more than {MAX_LUT_INPUTS} inputs"
+        );
+        assert_eq!(
+            table.len(),
+            1 << inputs.len(),
+            "LUT gate {id} needs one table entry per input combination"
+        );
+        Gate {
+            id,
+            gate_type: GateType::Lut,
+            inputs,
+            table: Some(table),
+        }
+    }
+
+    /// Output of the gate for every input combination, the first input being
+    /// the most significant bit of the row index
+    pub fn truth_table(&self) -> Vec<u8> {
+        let arity = self.inputs.len();
+        (0..1usize << arity)
+            .map(|row| {
+                let bits: Vec<u8> = (0..arity)
+                    .map(|i| ((row >> (arity - 1 - i)) & 1) as u8)
+                    .collect();
+                self.eval(&bits)
+            })
+            .collect()
+    }
+
+    /// Output of the gate on its input bits
+    ///
+    /// Panics if a LUT gate has no table or its table is too short.
+    pub fn eval(&self, bits: &[u8]) -> u8 {
+        match &self.table {
+            Some(table) if self.gate_type == GateType::Lut => {
+                let index = bits
+                    .iter()
+                    .fold(0, |index, &bit| (index << 1) | bit as usize);
+                table[index] & 1
+            }
+            _ => self
+                .gate_type
+                .eval(bits)
+                .unwrap_or_else(|| panic!("LUT gate {} has no table", self.id)),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -18,6 +265,28 @@ pub struct Circuit {
     pub alice: Option<Vec<u32>>,
     pub bob: Option<Vec<u32>>,
     pub out: Vec<u32>,
+    /// Public constant wires and their values, driven by no gate or party
+    #[serde(
+        default,
+        rename = "const",
+        deserialize_with = "deserialize_constants",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub constants: HashMap<u32, u8>,
+}
+
+/// Constant wires of a circuit file, whose values must be bits
+fn deserialize_constants<'de, D>(deserializer: D) -> Result<HashMap<u32, u8>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let constants = HashMap::<u32, u8>::deserialize(deserializer)?;
+    match constants.iter().find(|(_, &value)| value > 1) {
+        Some((wire, value)) => Err(serde::de::Error::custom(format!(
+            "constant wire {wire} holds {value}, which is not a bit"
+        ))),
+        None => Ok(constants),
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -26,18 +295,505 @@ pub struct CircuitCollection {
     pub circuits: Vec<Circuit>,
 }
 
+/// Structural problem found by `Circuit::validate`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CircuitError {
+    /// A wire is driven more than once, by gates, party inputs or constants
+    DuplicateWire(u32),
+    /// A gate has the wrong number of inputs for its type
+    WrongArity {
+        gate: u32,
+        expected: usize,
+        found: usize,
+    },
+    /// A LUT gate has no table, too many inputs, a table of the wrong size or
+    /// a table entry other than 0 or 1
+    InvalidLut(u32),
+    /// A gate reads a wire that nothing drives
+    UndefinedWire { gate: u32, wire: u32 },
+    /// A gate depends on its own output
+    Cycle(u32),
+    /// An output wire that nothing drives
+    UndrivenOutput(u32),
+    /// A constant wire holding a value other than 0 or 1
+    InvalidConstant { wire: u32, value: u8 },
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::DuplicateWire(wire) => write!(f, "wire {wire} is driven more than once"),
+            CircuitError::WrongArity {
+                gate,
+                expected,
+                found,
+            } => write!(f, "gate {gate} takes {expected} inputs but has {found}"),
+            CircuitError::InvalidLut(gate) => write!(
+                f,
+                "LUT gate {gate} needs at most {MAX_LUT_INPUTS} inputs and one bit per input combination"
+            ),
+            CircuitError::UndefinedWire { gate, wire } => {
+                write!(f, "gate {gate} reads wire {wire}, which nothing drives")
+            }
+            CircuitError::Cycle(gate) => write!(f, "gate {gate} is part of a cycle"),
+            CircuitError::UndrivenOutput(wire) => write!(f, "output wire {wire} is not driven"),
+            CircuitError::InvalidConstant { wire, value } => {
+                write!(f, "constant wire {wire} holds {value}, which is not a bit")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CircuitError {}
+
 impl Circuit {
-    pub fn from_json_file<P: AsRef<Path>>(
-        path: P,
-    ) -> Result<Vec<Circuit>, Box<dyn std::error::Error>> {
+    pub fn from_json_file<P: AsRef<Path>>(path: P) -> crate::Result<Vec<Circuit>> {
         let contents = fs::read_to_string(path)?;
         let collection: CircuitCollection = serde_json::from_str(&contents)?;
+        for circuit in &collection.circuits {
+            circuit.validate()?;
+        }
         Ok(collection.circuits)
     }
 
-    pub fn load_single<P: AsRef<Path>>(path: P) -> Result<Circuit, Box<dyn std::error::Error>> {
+    pub fn load_single<P: AsRef<Path>>(path: P) -> crate::Result<Circuit> {
         let contents = fs::read_to_string(path)?;
         let circuit: Circuit = serde_json::from_str(&contents)?;
+        circuit.validate()?;
         Ok(circuit)
     }
+
+    /// Party input wires, Alice's first
+    pub fn input_wires(&self) -> impl Iterator<Item = u32> + '_ {
+        self.alice.iter().chain(self.bob.iter()).flatten().copied()
+    }
+
+    /// Gates ordered so every gate comes after the gates driving its inputs
+    ///
+    /// Gates already in such an order keep it. Fails on duplicate wires,
+    /// constants and LUT entries that are not bits, wrong arities, undefined
+    /// wires, cycles and undriven outputs.
+    pub fn topological_order(&self) -> Result<Vec<&Gate>, CircuitError> {
+        // Driver of every wire: a gate index, or `None` for inputs and constants
+        let mut drivers: HashMap<u32, Option<usize>> = HashMap::new();
+        for wire in self.input_wires().chain(self.constants.keys().copied()) {
+            if drivers.insert(wire, None).is_some() {
+                return Err(CircuitError::DuplicateWire(wire));
+            }
+        }
+        if let Some((&wire, &value)) = self
+            .constants
+            .iter()
+            .filter(|(_, &value)| value > 1)
+            .min_by_key(|(&wire, _)| wire)
+        {
+            return Err(CircuitError::InvalidConstant { wire, value });
+        }
+        for (index, gate) in self.gates.iter().enumerate() {
+            if drivers.insert(gate.id, Some(index)).is_some() {
+                return Err(CircuitError::DuplicateWire(gate.id));
+            }
+            match gate.gate_type.arity() {
+                Some(expected) if expected != gate.inputs.len() => {
+                    return Err(CircuitError::WrongArity {
+                        gate: gate.id,
+                        expected,
+                        found: gate.inputs.len(),
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    let table_len = gate.table.as_ref().map(|table| table.len());
+                    let bits = gate.table.iter().flatten().all(|&entry| entry <= 1);
+                    if gate.inputs.len() > MAX_LUT_INPUTS
+                        || table_len != Some(1 << gate.inputs.len())
+                        || !bits
+                    {
+                        return Err(CircuitError::InvalidLut(gate.id));
+                    }
+                }
+            }
+        }
+        for gate in &self.gates {
+            if let Some(&wire) = gate.inputs.iter().find(|wire| !drivers.contains_key(wire)) {
+                return Err(CircuitError::UndefinedWire {
+                    gate: gate.id,
+                    wire,
+                });
+            }
+        }
+        if let Some(&wire) = self.out.iter().find(|wire| !drivers.contains_key(wire)) {
+            return Err(CircuitError::UndrivenOutput(wire));
+        }
+
+        // Depth-first from each gate in file order, emitting a gate once all
+        // its drivers are emitted. A gate met again while still open closes a
+        // cycle.
+        const UNVISITED: u8 = 0;
+        const OPEN: u8 = 1;
+        const DONE: u8 = 2;
+        let mut state = vec![UNVISITED; self.gates.len()];
+        let mut order = Vec::with_capacity(self.gates.len());
+        for root in 0..self.gates.len() {
+            if state[root] != UNVISITED {
+                continue;
+            }
+            state[root] = OPEN;
+            let mut stack = vec![(root, 0)];
+            while let Some((index, next_input)) = stack.pop() {
+                let gate = &self.gates[index];
+                let Some(wire) = gate.inputs.get(next_input) else {
+                    state[index] = DONE;
+                    order.push(gate);
+                    continue;
+                };
+                stack.push((index, next_input + 1));
+                if let Some(driver) = drivers[wire] {
+                    match state[driver] {
+                        UNVISITED => {
+                            state[driver] = OPEN;
+                            stack.push((driver, 0));
+                        }
+                        OPEN => return Err(CircuitError::Cycle(self.gates[driver].id)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Bit of every output wire for the given party input bits, computed in
+    /// the clear
+    ///
+    /// Gates follow the semantics the garbler gives them, and nonzero bits
+    /// count as 1 as they do when labels are chosen, so the result matches
+    /// `execute_secure_protocol`. Fails if the circuit is not well formed or
+    /// a party input is missing.
+    pub fn evaluate_plain(&self, inputs: &HashMap<u32, u8>) -> crate::Result<HashMap<u32, u8>> {
+        let mut values: HashMap<u32, u8> = HashMap::new();
+        for wire in self.input_wires() {
+            let bit = inputs.get(&wire).ok_or(crate::Error::MissingInput(wire))?;
+            values.insert(wire, u8::from(*bit != 0));
+        }
+        for (&wire, &value) in &self.constants {
+            values.insert(wire, u8::from(value != 0));
+        }
+        for gate in self.topological_order()? {
+            let bits: Vec<u8> = gate.inputs.iter().map(|wire| values[wire]).collect();
+            values.insert(gate.id, gate.eval(&bits));
+        }
+        Ok(self.out.iter().map(|&wire| (wire, values[&wire])).collect())
+    }
+
+    /// Check the circuit is well formed
+    ///
+    /// Party inputs nothing reads are allowed, see `unused_inputs`.
+    pub fn validate(&self) -> Result<(), CircuitError> {
+        self.topological_order().map(|_| ())
+    }
+
+    /// Party input wires that no gate reads and that are not outputs, Alice's
+    /// first
+    pub fn unused_inputs(&self) -> Vec<u32> {
+        let used: HashSet<u32> = self
+            .gates
+            .iter()
+            .flat_map(|gate| gate.inputs.iter())
+            .chain(self.out.iter())
+            .copied()
+            .collect();
+        self.input_wires()
+            .filter(|wire| !used.contains(wire))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circuit_with_gates(gates: Vec<Gate>, out: Vec<u32>) -> Circuit {
+        Circuit {
+            id: "test".to_string(),
+            gates,
+            alice: Some(vec![1]),
+            bob: Some(vec![2]),
+            out,
+            constants: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_two_input_functions_are_distinct() {
+        // The constants extended to two inputs plus the binary types give all
+        // sixteen tables
+        let mut tables: Vec<u8> = GateType::ALL
+            .iter()
+            .filter(|gate_type| gate_type.arity() == Some(2))
+            .map(|gate_type| gate_type.truth_table().unwrap())
+            .chain([0b0000, 0b1111])
+            .collect();
+        tables.sort();
+        tables.dedup();
+        assert_eq!(tables, (0..16).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_gate_forms() {
+        for gate_type in GateType::ALL {
+            let Some(arity) = gate_type.arity() else {
+                // LUTs are always garbled as tables
+                assert_eq!(gate_type.and_form(), None);
+                assert_eq!(gate_type.affine_form(), None);
+                continue;
+            };
+            for row in 0..1usize << arity {
+                let bits: Vec<u8> = (0..arity)
+                    .map(|i| ((row >> (arity - 1 - i)) & 1) as u8)
+                    .collect();
+                let output = gate_type.eval(&bits).unwrap();
+
+                if let Some((alpha, beta, gamma)) = gate_type.and_form() {
+                    assert_eq!(((bits[0] ^ alpha) & (bits[1] ^ beta)) ^ gamma, output);
+                }
+                if let Some((coefficients, constant)) = gate_type.affine_form() {
+                    let affine = bits
+                        .iter()
+                        .zip(&coefficients)
+                        .fold(constant, |acc, (bit, coefficient)| {
+                            acc ^ (bit & coefficient)
+                        });
+                    assert_eq!(affine, output);
+                }
+            }
+
+            // Every gate with inputs is either AND-like or affine
+            let forms = [
+                gate_type.and_form().is_some(),
+                gate_type.affine_form().is_some(),
+            ];
+            assert_eq!(
+                forms.iter().filter(|&&form| form).count(),
+                usize::from(arity > 0)
+            );
+        }
+        assert_eq!(GateType::Or.and_form(), Some((1, 1, 1)));
+        assert_eq!(GateType::Xnor.affine_form(), Some((vec![1, 1], 1)));
+    }
+
+    #[test]
+    fn test_lut_eval() {
+        // 3-input majority, first input most significant
+        let gate = Gate::lut(4, vec![1, 2, 3], vec![0, 0, 0, 1, 0, 1, 1, 1]);
+        for row in 0..8u8 {
+            let bits = [row >> 2, (row >> 1) & 1, row & 1];
+            let majority = u8::from(bits.iter().sum::<u8>() >= 2);
+            assert_eq!(gate.eval(&bits), majority);
+        }
+
+        assert_eq!(Gate::new(3, GateType::Nor, vec![1, 2]).eval(&[0, 0]), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_lut_table_size() {
+        Gate::lut(4, vec![1, 2], vec![0, 1, 1]);
+    }
+
+    #[test]
+    fn test_gate_type_names() {
+        let gate: Gate =
+            serde_json::from_str(r#"{"id": 3, "type": "NAND", "in": [1, 2]}"#).unwrap();
+        assert_eq!(gate.gate_type, GateType::Nand);
+
+        for (name, gate_type) in [
+            ("\"ANDNOT\"", GateType::NImply),
+            ("\"EQW\"", GateType::Buf),
+            ("\"INV\"", GateType::Not),
+            ("\"CNIMPLY\"", GateType::CNImply),
+        ] {
+            assert_eq!(serde_json::from_str::<GateType>(name).unwrap(), gate_type);
+        }
+        assert_eq!(
+            serde_json::to_string(&GateType::ProjA).unwrap(),
+            "\"PROJA\""
+        );
+
+        // LUT gates carry their table, other gates omit it
+        let gate: Gate = serde_json::from_str(
+            r#"{"id": 4, "type": "LUT", "in": [1, 2], "table": [0, 1, 1, 1]}"#,
+        )
+        .unwrap();
+        assert_eq!(gate.table, Some(vec![0, 1, 1, 1]));
+        assert!(
+            !serde_json::to_string(&Gate::new(3, GateType::And, vec![1, 2]))
+                .unwrap()
+                .contains("table")
+        );
+
+        // Constant wires are keyed by wire id
+        let circuit: Circuit = serde_json::from_str(
+            r#"{"id": "c", "gates": [], "alice": [1], "bob": null, "out": [5], "const": {"5": 1}}"#,
+        )
+        .unwrap();
+        assert_eq!(circuit.constants[&5], 1);
+        assert!(serde_json::from_str::<Circuit>(
+            r#"{"id": "c", "gates": [], "alice": [1], "bob": null, "out": [5], "const": {"5": 7}}"#,
+        )
+        .is_err());
+
+        // Unknown types are rejected when the circuit is parsed
+        assert!(serde_json::from_str::<Gate>(r#"{"id": 3, "type": "MUX", "in": [1, 2]}"#).is_err());
+    }
+
+    #[test]
+    fn test_topological_order() {
+        // Listed out of order: 5 reads 4 before 4 is defined
+        let circuit = circuit_with_gates(
+            vec![
+                Gate::new(5, GateType::Or, vec![4, 2]),
+                Gate::new(4, GateType::And, vec![1, 2]),
+                Gate::new(6, GateType::Not, vec![5]),
+            ],
+            vec![6],
+        );
+        let order: Vec<u32> = circuit
+            .topological_order()
+            .unwrap()
+            .iter()
+            .map(|gate| gate.id)
+            .collect();
+        assert_eq!(order, vec![4, 5, 6]);
+        assert_eq!(circuit.validate(), Ok(()));
+
+        // Already sorted circuits keep their order
+        let sorted = circuit_with_gates(
+            vec![
+                Gate::new(4, GateType::And, vec![1, 2]),
+                Gate::new(3, GateType::Xor, vec![1, 2]),
+                Gate::new(5, GateType::Or, vec![4, 3]),
+            ],
+            vec![5],
+        );
+        let order: Vec<u32> = sorted
+            .topological_order()
+            .unwrap()
+            .iter()
+            .map(|gate| gate.id)
+            .collect();
+        assert_eq!(order, vec![4, 3, 5]);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let cases = [
+            (
+                circuit_with_gates(
+                    vec![
+                        Gate::new(3, GateType::And, vec![1, 2]),
+                        Gate::new(3, GateType::Or, vec![1, 2]),
+                    ],
+                    vec![3],
+                ),
+                CircuitError::DuplicateWire(3),
+            ),
+            (
+                circuit_with_gates(vec![Gate::new(2, GateType::Not, vec![1])], vec![2]),
+                CircuitError::DuplicateWire(2),
+            ),
+            (
+                circuit_with_gates(vec![Gate::new(3, GateType::And, vec![1])], vec![3]),
+                CircuitError::WrongArity {
+                    gate: 3,
+                    expected: 2,
+                    found: 1,
+                },
+            ),
+            (
+                circuit_with_gates(vec![Gate::new(3, GateType::Lut, vec![1, 2])], vec![3]),
+                CircuitError::InvalidLut(3),
+            ),
+            (
+                circuit_with_gates(vec![Gate::lut(3, vec![1, 2], vec![0, 0, 0, 2])], vec![3]),
+                CircuitError::InvalidLut(3),
+            ),
+            (
+                circuit_with_gates(vec![Gate::new(3, GateType::And, vec![1, 9])], vec![3]),
+                CircuitError::UndefinedWire { gate: 3, wire: 9 },
+            ),
+            (
+                circuit_with_gates(
+                    vec![
+                        Gate::new(3, GateType::And, vec![1, 5]),
+                        Gate::new(4, GateType::Or, vec![3, 2]),
+                        Gate::new(5, GateType::Xor, vec![4, 2]),
+                    ],
+                    vec![5],
+                ),
+                CircuitError::Cycle(3),
+            ),
+            (
+                circuit_with_gates(vec![Gate::new(3, GateType::And, vec![1, 2])], vec![7]),
+                CircuitError::UndrivenOutput(7),
+            ),
+            (
+                Circuit {
+                    constants: HashMap::from([(5, 2)]),
+                    ..circuit_with_gates(vec![Gate::new(3, GateType::And, vec![1, 5])], vec![3])
+                },
+                CircuitError::InvalidConstant { wire: 5, value: 2 },
+            ),
+        ];
+
+        for (circuit, expected) in cases {
+            assert_eq!(circuit.validate(), Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_unused_inputs() {
+        // Bob's input is never read, which is reported but still valid
+        let circuit = circuit_with_gates(vec![Gate::new(3, GateType::Not, vec![1])], vec![3]);
+        assert_eq!(circuit.validate(), Ok(()));
+        assert_eq!(circuit.unused_inputs(), vec![2]);
+
+        // An input that is also an output counts as used
+        let circuit = circuit_with_gates(vec![Gate::new(3, GateType::Not, vec![1])], vec![3, 2]);
+        assert!(circuit.unused_inputs().is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_plain() {
+        let mut circuit = circuit_with_gates(
+            vec![
+                Gate::new(5, GateType::Or, vec![4, 2]),
+                Gate::new(4, GateType::NImply, vec![1, 2]),
+                Gate::lut(6, vec![1, 2, 3], vec![0, 1, 1, 0, 1, 0, 0, 1]),
+            ],
+            vec![5, 6, 3],
+        );
+        circuit.constants.insert(3, 1);
+        for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let inputs = HashMap::from([(1, a), (2, b)]);
+            let outputs = circuit.evaluate_plain(&inputs).unwrap();
+            let expected = HashMap::from([(5, (a & (1 - b)) | b), (6, 1 ^ a ^ b), (3, 1)]);
+            assert_eq!(outputs, expected, "{a} and {b}");
+        }
+
+        // Nonzero bits count as 1, missing ones are reported
+        let outputs = circuit.evaluate_plain(&HashMap::from([(1, 2), (2, 0)]));
+        assert_eq!(outputs.unwrap()[&5], 1);
+        assert!(matches!(
+            circuit.evaluate_plain(&HashMap::from([(1, 1)])),
+            Err(crate::Error::MissingInput(2))
+        ));
+    }
+
+    #[test]
+    fn test_bundled_circuits_validate() {
+        for path in ["circuits/bool.json", "circuits/max.json"] {
+            assert!(Circuit::from_json_file(path).is_ok(), "{path}");
+        }
+    }
 }
@@ -0,0 +1,401 @@
+use crate::circuit::{Circuit, Gate};
+use crate::error::{Error, Result};
+use crate::simulate::{Simulator, Vectors};
+use std::collections::HashMap;
+
+/// Input vector on which two circuits differ, with the outputs of each
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Counterexample {
+    pub alice: Vec<u8>,
+    pub bob: Vec<u8>,
+    /// Outputs of the first circuit
+    pub left: Vec<u8>,
+    /// Outputs of the second circuit
+    pub right: Vec<u8>,
+}
+
+/// Outcome of an equivalence check
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Equivalence {
+    /// Same outputs on every input vector
+    Equivalent,
+    /// Different outputs on the counterexample
+    Different(Counterexample),
+    /// Same outputs on `vectors` random input vectors, but the miter outgrew
+    /// the BDD node limit before it could be decided
+    Unknown { vectors: u64 },
+}
+
+/// Settings of an equivalence check
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checker {
+    /// Most inputs checked by simulating every input vector
+    pub exhaustive_inputs: usize,
+    /// Random vectors simulated before building the miter of wider circuits
+    pub random_vectors: u64,
+    pub seed: u64,
+    /// Most BDD nodes the miter may build
+    pub bdd_nodes: usize,
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Checker {
+            exhaustive_inputs: 20,
+            random_vectors: 1 << 16,
+            seed: 0,
+            bdd_nodes: 1 << 20,
+        }
+    }
+}
+
+impl Checker {
+    /// Check whether two circuits compute the same function, matching Alice's
+    /// inputs, Bob's inputs and the outputs of both by position
+    ///
+    /// Circuits with up to `exhaustive_inputs` inputs are simulated on every
+    /// input vector. Wider ones are simulated on random vectors, then their
+    /// outputs are compared as BDDs. Fails if either circuit is not well
+    /// formed or if their numbers of inputs or outputs differ.
+    pub fn check(&self, left: &Circuit, right: &Circuit) -> Result<Equivalence> {
+        let alice = left.alice.iter().flatten().count();
+        let ports = |circuit: &Circuit| {
+            (
+                circuit.alice.iter().flatten().count(),
+                circuit.bob.iter().flatten().count(),
+                circuit.out.len(),
+            )
+        };
+        if ports(left) != ports(right) {
+            let (a, b, o) = ports(left);
+            let (c, d, p) = ports(right);
+            return Err(Error::PortMismatch(format!(
+                "{} has {a} Alice inputs, {b} Bob inputs and {o} outputs, but {} has {c}, {d} and {p}",
+                left.id, right.id
+            )));
+        }
+        let counterexample = |inputs: Vec<u8>| -> Result<Equivalence> {
+            Ok(Equivalence::Different(Counterexample {
+                left: outputs(left, &inputs)?,
+                right: outputs(right, &inputs)?,
+                bob: inputs[alice..].to_vec(),
+                alice: inputs[..alice].to_vec(),
+            }))
+        };
+
+        let simulator = Simulator::new(left)?;
+        let other = Simulator::new(right)?;
+        let vectors = if simulator.input_count() <= self.exhaustive_inputs {
+            Vectors::Exhaustive
+        } else {
+            Vectors::Random {
+                count: self.random_vectors,
+                seed: self.seed,
+            }
+        };
+        let mut mismatch = None;
+        simulator.simulate::<u128>(vectors, |batch| {
+            if let Some(lane) = batch.first_difference(&other.run(&batch.inputs)) {
+                mismatch = Some(batch.input_bits(lane));
+            }
+            mismatch.is_none()
+        });
+        if let Some(inputs) = mismatch {
+            return counterexample(inputs);
+        }
+        if vectors == Vectors::Exhaustive {
+            return Ok(Equivalence::Equivalent);
+        }
+
+        // Interleave the inputs of Alice and Bob in the variable order, which
+        // keeps the BDDs of arithmetic on their numbers small
+        let bob = simulator.input_count() - alice;
+        let mut order = Vec::new();
+        for i in 0..alice.max(bob) {
+            order.extend((i < alice).then_some(i));
+            order.extend((i < bob).then_some(alice + i));
+        }
+        let mut variables = vec![0; order.len()];
+        for (variable, &input) in order.iter().enumerate() {
+            variables[input] = variable as u32;
+        }
+
+        let (left_gates, right_gates) = (left.topological_order()?, right.topological_order()?);
+        let mut bdd = Bdd::new(self.bdd_nodes);
+        let miter = bdd
+            .outputs(left, &left_gates, &variables)
+            .and_then(|left_outputs| {
+                let right_outputs = bdd.outputs(right, &right_gates, &variables)?;
+                for (&a, &b) in left_outputs.iter().zip(&right_outputs) {
+                    if a != b {
+                        return Ok(Some(bdd.xor(a, b)?));
+                    }
+                }
+                Ok(None)
+            });
+        match miter {
+            Ok(None) => Ok(Equivalence::Equivalent),
+            Ok(Some(difference)) => {
+                let assignment = bdd.satisfy(difference, variables.len());
+                counterexample(variables.iter().map(|&v| assignment[v as usize]).collect())
+            }
+            Err(NodeLimit) => Ok(Equivalence::Unknown {
+                vectors: self.random_vectors,
+            }),
+        }
+    }
+}
+
+impl Circuit {
+    /// Check whether another circuit computes the same function, with the
+    /// default `Checker`
+    pub fn check_equivalence(&self, other: &Circuit) -> Result<Equivalence> {
+        Checker::default().check(self, other)
+    }
+}
+
+/// Outputs of a circuit for an input vector in the order of `input_wires`
+fn outputs(circuit: &Circuit, inputs: &[u8]) -> Result<Vec<u8>> {
+    let values = circuit.input_wires().zip(inputs.iter().copied()).collect();
+    let outputs = circuit.evaluate_plain(&values)?;
+    Ok(circuit.out.iter().map(|wire| outputs[wire]).collect())
+}
+
+const FALSE: u32 = 0;
+const TRUE: u32 = 1;
+
+/// The BDD node limit was reached
+#[derive(Debug)]
+struct NodeLimit;
+
+/// Reduced ordered binary decision diagrams sharing one node table, so equal
+/// functions are the same node
+struct Bdd {
+    /// Variable, low and high child of every node, the two terminals first
+    nodes: Vec<(u32, u32, u32)>,
+    unique: HashMap<(u32, u32, u32), u32>,
+    cache: HashMap<(u32, u32, u32), u32>,
+    limit: usize,
+}
+
+impl Bdd {
+    fn new(limit: usize) -> Self {
+        Bdd {
+            nodes: vec![(u32::MAX, FALSE, FALSE), (u32::MAX, TRUE, TRUE)],
+            unique: HashMap::new(),
+            cache: HashMap::new(),
+            limit,
+        }
+    }
+
+    fn node(&mut self, variable: u32, low: u32, high: u32) -> std::result::Result<u32, NodeLimit> {
+        if low == high {
+            return Ok(low);
+        }
+        if let Some(&node) = self.unique.get(&(variable, low, high)) {
+            return Ok(node);
+        }
+        if self.nodes.len() >= self.limit {
+            return Err(NodeLimit);
+        }
+        let node = self.nodes.len() as u32;
+        self.nodes.push((variable, low, high));
+        self.unique.insert((variable, low, high), node);
+        Ok(node)
+    }
+
+    /// Children of a node for a variable at or above its own
+    fn cofactors(&self, node: u32, variable: u32) -> (u32, u32) {
+        match self.nodes[node as usize] {
+            (own, low, high) if own == variable => (low, high),
+            _ => (node, node),
+        }
+    }
+
+    /// `if f then g else h`
+    fn ite(&mut self, f: u32, g: u32, h: u32) -> std::result::Result<u32, NodeLimit> {
+        match (f, g, h) {
+            (TRUE, _, _) => return Ok(g),
+            (FALSE, _, _) => return Ok(h),
+            _ if g == h => return Ok(g),
+            (_, TRUE, FALSE) => return Ok(f),
+            _ => {}
+        }
+        if let Some(&node) = self.cache.get(&(f, g, h)) {
+            return Ok(node);
+        }
+        let variable = [f, g, h]
+            .iter()
+            .map(|&node| self.nodes[node as usize].0)
+            .min()
+            .unwrap_or(u32::MAX);
+        let (f0, f1) = self.cofactors(f, variable);
+        let (g0, g1) = self.cofactors(g, variable);
+        let (h0, h1) = self.cofactors(h, variable);
+        let low = self.ite(f0, g0, h0)?;
+        let high = self.ite(f1, g1, h1)?;
+        let node = self.node(variable, low, high)?;
+        if self.cache.len() >= self.limit {
+            self.cache.clear();
+        }
+        self.cache.insert((f, g, h), node);
+        Ok(node)
+    }
+
+    fn xor(&mut self, f: u32, g: u32) -> std::result::Result<u32, NodeLimit> {
+        let not_g = self.ite(g, FALSE, TRUE)?;
+        self.ite(f, not_g, g)
+    }
+
+    /// Output functions of a circuit, given its gates in topological order
+    /// and the variable of each input in the order of `input_wires`
+    fn outputs(
+        &mut self,
+        circuit: &Circuit,
+        gates: &[&Gate],
+        variables: &[u32],
+    ) -> std::result::Result<Vec<u32>, NodeLimit> {
+        let mut wires: HashMap<u32, u32> = HashMap::new();
+        for (wire, &variable) in circuit.input_wires().zip(variables) {
+            wires.insert(wire, self.node(variable, FALSE, TRUE)?);
+        }
+        for (&wire, &value) in &circuit.constants {
+            wires.insert(wire, if value != 0 { TRUE } else { FALSE });
+        }
+        for gate in gates {
+            // Select between row pairs on each input, the last first
+            let mut rows: Vec<u32> = gate
+                .truth_table()
+                .iter()
+                .map(|&bit| if bit & 1 == 1 { TRUE } else { FALSE })
+                .collect();
+            for wire in gate.inputs.iter().rev() {
+                let input = wires[wire];
+                rows = rows
+                    .chunks(2)
+                    .map(|pair| self.ite(input, pair[1], pair[0]))
+                    .collect::<std::result::Result<_, _>>()?;
+            }
+            wires.insert(gate.id, rows[0]);
+        }
+        Ok(circuit.out.iter().map(|wire| wires[wire]).collect())
+    }
+
+    /// Assignment of the variables, unset ones being 0, on which a function
+    /// other than FALSE is true
+    fn satisfy(&self, mut node: u32, variables: usize) -> Vec<u8> {
+        let mut assignment = vec![0; variables];
+        while node != TRUE {
+            let (variable, low, high) = self.nodes[node as usize];
+            if low == FALSE {
+                assignment[variable as usize] = 1;
+                node = high;
+            } else {
+                node = low;
+            }
+        }
+        assignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{Bit, CircuitBuilder};
+    use crate::circuit::GateType;
+    use crate::gadgets::{lookahead_add, ripple_add, Variant};
+
+    /// Textbook ripple-carry or optimized carry-lookahead adder of two
+    /// `width`-bit numbers
+    fn adder(width: usize, lookahead: bool) -> Circuit {
+        let builder = CircuitBuilder::new();
+        let a: Vec<Bit> = (0..width).map(|_| builder.alice_bit()).collect();
+        let b: Vec<Bit> = (0..width).map(|_| builder.bob_bit()).collect();
+        let (sum, _) = if lookahead {
+            lookahead_add(&a, &b, Variant::AndOptimized)
+        } else {
+            ripple_add(&a, &b, Variant::Textbook)
+        };
+        for bit in &sum {
+            builder.output(bit);
+        }
+        builder.build("adder").unwrap()
+    }
+
+    #[test]
+    fn test_equivalent_circuits() {
+        for circuit in crate::test_support::round_trip_circuits() {
+            let optimized = circuit.optimize().unwrap();
+            assert_eq!(
+                circuit.check_equivalence(&optimized).unwrap(),
+                Equivalence::Equivalent,
+                "{}",
+                circuit.id
+            );
+        }
+
+        // 64 inputs are beyond enumeration, so the BDD miter decides
+        let (left, right) = (adder(32, false), adder(32, true));
+        assert_eq!(
+            left.check_equivalence(&right).unwrap(),
+            Equivalence::Equivalent
+        );
+    }
+
+    #[test]
+    fn test_counterexample() {
+        // The top bit of the sum is negated
+        let left = adder(32, false);
+        let mut right = left.clone();
+        let top = *right.out.last().unwrap();
+        let gate = right.gates.iter_mut().find(|gate| gate.id == top).unwrap();
+        assert_eq!(gate.gate_type, GateType::Xor);
+        gate.gate_type = GateType::Xnor;
+
+        let checkers = [
+            Checker::default(),
+            // Random vectors miss nothing here, so this one is left to the BDDs
+            Checker {
+                random_vectors: 0,
+                ..Checker::default()
+            },
+        ];
+        for checker in checkers {
+            let Equivalence::Different(counterexample) = checker.check(&left, &right).unwrap()
+            else {
+                panic!("the circuits differ");
+            };
+            assert_ne!(counterexample.left, counterexample.right);
+            let inputs: Vec<u8> = [counterexample.alice, counterexample.bob].concat();
+            assert_eq!(counterexample.left, outputs(&left, &inputs).unwrap());
+        }
+
+        // Small circuits are compared on every vector
+        let left = adder(4, false);
+        let mut right = left.clone();
+        right.gates.retain(|gate| gate.id != right.out[3]);
+        right.constants.insert(right.out[3], 0);
+        let Equivalence::Different(counterexample) = left.check_equivalence(&right).unwrap() else {
+            panic!("the circuits differ");
+        };
+        assert_eq!((counterexample.left[3], counterexample.right[3]), (1, 0));
+    }
+
+    #[test]
+    fn test_port_mismatch_and_node_limit() {
+        assert!(matches!(
+            adder(4, false).check_equivalence(&adder(3, false)),
+            Err(Error::PortMismatch(_))
+        ));
+
+        let checker = Checker {
+            random_vectors: 128,
+            bdd_nodes: 64,
+            ..Checker::default()
+        };
+        assert_eq!(
+            checker.check(&adder(32, false), &adder(32, true)).unwrap(),
+            Equivalence::Unknown { vectors: 128 }
+        );
+    }
+}
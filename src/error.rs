@@ -0,0 +1,85 @@
+use crate::circuit::CircuitError;
+use std::fmt;
+
+/// Every failure the library reports
+#[derive(Debug)]
+pub enum Error {
+    /// The circuit is not well formed
+    MalformedCircuit(CircuitError),
+    /// No label or bit was given for an input wire
+    MissingInput(u32),
+    /// A ciphertext could not be decrypted under the given key
+    Decryption(&'static str),
+    /// A step of the oblivious transfer protocol failed
+    Ot(String),
+    /// An output label matches neither label of its wire
+    InvalidOutputLabel(u32),
+    /// Reading a circuit file failed
+    Io(std::io::Error),
+    /// A circuit file is not valid JSON for the circuit schema
+    Parse(serde_json::Error),
+    /// A line of a text circuit file does not follow its format
+    Syntax { line: usize, message: String },
+    /// An imported netlist uses something a circuit cannot represent
+    Import(String),
+    /// Circuits compared with each other differ in their numbers of inputs
+    /// or outputs
+    PortMismatch(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn syntax(line: usize, message: impl Into<String>) -> Self {
+        Error::Syntax {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MalformedCircuit(error) => write!(f, "malformed circuit: {error}"),
+            Error::MissingInput(wire) => write!(f, "missing input for wire {wire}"),
+            Error::Decryption(reason) => write!(f, "decryption failed: {reason}"),
+            Error::Ot(reason) => write!(f, "oblivious transfer failed: {reason}"),
+            Error::InvalidOutputLabel(wire) => write!(f, "invalid label for output wire {wire}"),
+            Error::Io(error) => write!(f, "I/O error: {error}"),
+            Error::Parse(error) => write!(f, "parse error: {error}"),
+            Error::Syntax { line, message } => write!(f, "syntax error on line {line}: {message}"),
+            Error::Import(reason) => write!(f, "cannot import netlist: {reason}"),
+            Error::PortMismatch(reason) => write!(f, "ports do not match: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::MalformedCircuit(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::Parse(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<CircuitError> for Error {
+    fn from(error: CircuitError) -> Self {
+        Error::MalformedCircuit(error)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Parse(error)
+    }
+}
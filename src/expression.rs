@@ -0,0 +1,261 @@
+use crate::builder::{Bit, CircuitBuilder};
+use crate::circuit::Circuit;
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'a> {
+    Name(&'a str),
+    Constant(bool),
+    Symbol(char),
+}
+
+/// Tokens of one statement
+fn tokenize(line: usize, statement: &str) -> Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = statement.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let length = if c.is_ascii_alphabetic() || c == '_' {
+            let length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(&rest[..length]));
+            length
+        } else if c == '0' || c == '1' {
+            tokens.push(Token::Constant(c == '1'));
+            1
+        } else if "=&|^!()".contains(c) {
+            tokens.push(Token::Symbol(c));
+            1
+        } else {
+            return Err(Error::syntax(line, format!("unexpected `{c}`")));
+        };
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Recursive-descent parser recording the gates of one expression, with
+/// `!` binding tightest, then `&`, `^` and `|`
+struct Parser<'a, 'b> {
+    tokens: &'b [Token<'a>],
+    position: usize,
+    line: usize,
+    names: &'b HashMap<&'a str, Bit>,
+    builder: &'b CircuitBuilder,
+}
+
+impl<'a> Parser<'a, '_> {
+    fn next_if(&mut self, symbol: char) -> bool {
+        let found = self.tokens.get(self.position) == Some(&Token::Symbol(symbol));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn binary(&mut self, symbol: char, operand: fn(&mut Self) -> Result<Bit>) -> Result<Bit> {
+        let mut value = operand(self)?;
+        while self.next_if(symbol) {
+            let right = operand(self)?;
+            value = match symbol {
+                '|' => value | right,
+                '^' => value ^ right,
+                _ => value & right,
+            };
+        }
+        Ok(value)
+    }
+
+    fn or(&mut self) -> Result<Bit> {
+        self.binary('|', Self::xor)
+    }
+
+    fn xor(&mut self) -> Result<Bit> {
+        self.binary('^', Self::and)
+    }
+
+    fn and(&mut self) -> Result<Bit> {
+        self.binary('&', Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Bit> {
+        let token = self.tokens.get(self.position).copied();
+        self.position += 1;
+        match token {
+            Some(Token::Symbol('!')) => Ok(!self.unary()?),
+            Some(Token::Symbol('(')) => {
+                let value = self.or()?;
+                if !self.next_if(')') {
+                    return Err(Error::syntax(self.line, "expected `)`"));
+                }
+                Ok(value)
+            }
+            Some(Token::Name(name)) => self
+                .names
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::syntax(self.line, format!("`{name}` is not defined"))),
+            Some(Token::Constant(value)) => Ok(self.builder.constant(value)),
+            Some(Token::Symbol(symbol)) => {
+                Err(Error::syntax(self.line, format!("unexpected `{symbol}`")))
+            }
+            None => Err(Error::syntax(self.line, "expression ends early")),
+        }
+    }
+}
+
+/// Fail if `name` is already defined or is a keyword
+fn check_new_name(names: &HashMap<&str, Bit>, line: usize, name: &str) -> Result<()> {
+    if names.contains_key(name) || ["alice", "bob", "out"].contains(&name) {
+        return Err(Error::syntax(line, format!("`{name}` is already defined")));
+    }
+    Ok(())
+}
+
+impl Circuit {
+    /// Compile a circuit from the expression language
+    ///
+    /// Statements are separated by newlines or `;`, and `#` starts a comment.
+    /// `alice a0 a1` and `bob b0 b1` declare each party's inputs in order,
+    /// `name = expression` names a value and `out = expression` appends an
+    /// output. Expressions combine names and the constants `0` and `1` with
+    /// `!`, `&`, `^` and `|`, binding in that order, and parentheses.
+    pub fn from_expression(id: &str, text: &str) -> Result<Circuit> {
+        let builder = CircuitBuilder::new();
+        let mut names: HashMap<&str, Bit> = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let code = line.split('#').next().unwrap_or_default();
+            for statement in code.split(';') {
+                let tokens = tokenize(line_number, statement)?;
+                match tokens[..] {
+                    [] => {}
+                    [Token::Name(party @ ("alice" | "bob")), ref inputs @ ..] => {
+                        for token in inputs {
+                            let Token::Name(name) = *token else {
+                                return Err(Error::syntax(line_number, "expected input names"));
+                            };
+                            check_new_name(&names, line_number, name)?;
+                            let bit = match party {
+                                "alice" => builder.alice_bit(),
+                                _ => builder.bob_bit(),
+                            };
+                            names.insert(name, bit);
+                        }
+                    }
+                    [Token::Name(name), Token::Symbol('='), ref expression @ ..] => {
+                        let mut parser = Parser {
+                            tokens: expression,
+                            position: 0,
+                            line: line_number,
+                            names: &names,
+                            builder: &builder,
+                        };
+                        let value = parser.or()?;
+                        if parser.position < expression.len() {
+                            return Err(Error::syntax(
+                                line_number,
+                                "expected the end of the statement",
+                            ));
+                        }
+                        if name == "out" {
+                            builder.output(&value);
+                        } else {
+                            check_new_name(&names, line_number, name)?;
+                            names.insert(name, value);
+                        }
+                    }
+                    _ => {
+                        return Err(Error::syntax(
+                            line_number,
+                            "expected an input declaration or an assignment",
+                        ))
+                    }
+                }
+            }
+        }
+        builder.build(id)
+    }
+
+    /// Load a circuit in the expression language, named after the file stem
+    pub fn load_expression<P: AsRef<Path>>(path: P) -> Result<Circuit> {
+        let path = path.as_ref();
+        let id = path.file_stem().unwrap_or_default().to_string_lossy();
+        Circuit::from_expression(&id, &fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::GateType;
+    use crate::test_support::evaluate;
+
+    #[test]
+    fn test_expression() {
+        let text = "alice a0 a1; bob b0 b1\nout = (a0 & b0) | !(a1 ^ b1)\n";
+        let circuit = Circuit::from_expression("example", text).unwrap();
+        assert_eq!(circuit.alice, Some(vec![0, 1]));
+        assert_eq!(circuit.bob, Some(vec![2, 3]));
+        for inputs in 0..16u8 {
+            let bits: Vec<u8> = (0..4).map(|i| (inputs >> i) & 1).collect();
+            let [a0, a1, b0, b1] = bits[..] else {
+                unreachable!()
+            };
+            let expected = (a0 & b0) | (1 ^ a1 ^ b1);
+            assert_eq!(evaluate(&circuit, &bits), vec![expected]);
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        // `!` binds tightest, then `&`, `^` and `|`
+        let text = "
+            alice a b  # Alice's bits
+            bob c
+            t = a | b ^ c & !a
+            out = t; out = 1 ^ a
+        ";
+        let circuit = Circuit::from_expression("precedence", text).unwrap();
+        let types: Vec<GateType> = circuit.gates.iter().map(|gate| gate.gate_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                GateType::Not,
+                GateType::And,
+                GateType::Xor,
+                GateType::Or,
+                GateType::Xor
+            ]
+        );
+        for inputs in 0..8u8 {
+            let [a, b, c] = [inputs & 1, (inputs >> 1) & 1, inputs >> 2];
+            let t = a | (b ^ (c & (1 - a)));
+            assert_eq!(evaluate(&circuit, &[a, b, c]), vec![t, 1 ^ a]);
+        }
+    }
+
+    #[test]
+    fn test_expression_errors() {
+        let line = |text: &str| match Circuit::from_expression("error", text) {
+            Err(Error::Syntax { line, .. }) => Some(line),
+            _ => None,
+        };
+        assert_eq!(line("alice a\nbob b\nout = a & c"), Some(3));
+        assert_eq!(line("alice a; bob a"), Some(1));
+        assert_eq!(line("alice a\nbob b\nout = (a & b"), Some(3));
+        assert_eq!(line("alice a\nbob b\nout = a b"), Some(3));
+        assert_eq!(line("alice a\n\nbob b\nout = a + b"), Some(4));
+        assert_eq!(line("alice a\nbob b\na & b"), Some(3));
+
+        // Unused inputs are kept and reported
+        let circuit = Circuit::from_expression("unused", "alice a b; bob c\nout = a & c").unwrap();
+        assert_eq!(
+            circuit.unused_inputs(),
+            vec![circuit.alice.as_ref().unwrap()[1]]
+        );
+    }
+}
@@ -0,0 +1,667 @@
+use crate::builder::Bit;
+
+/// Construction a gadget records
+///
+/// Under Free XOR only AND-like gates such as AND and OR need a garbled
+/// table, so the optimized constructions trade them for XOR and NOT gates.
+/// Gadgets that are already optimal in AND gates, such as `equal`, have a
+/// single construction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Variant {
+    /// Textbook construction, as in circuits assembled gate by gate
+    Textbook,
+    /// Construction with the fewest AND-like gates
+    #[default]
+    AndOptimized,
+}
+
+fn zero(like: &Bit) -> Bit {
+    like.builder().constant(false)
+}
+
+/// `if_true` when `select` is 1 and `if_false` otherwise
+fn mux(select: &Bit, if_true: &Bit, if_false: &Bit, variant: Variant) -> Bit {
+    match variant {
+        Variant::Textbook => &(select & if_true) | &(&!select & if_false),
+        Variant::AndOptimized => select.mux(if_true, if_false),
+    }
+}
+
+/// Carry out of a full adder
+fn full_carry(a: &Bit, b: &Bit, carry: &Bit, variant: Variant) -> Bit {
+    match variant {
+        Variant::Textbook => &(a & b) | &(carry & &(a ^ b)),
+        Variant::AndOptimized => carry ^ &(&(a ^ carry) & &(b ^ carry)),
+    }
+}
+
+/// Ripple-carry sum of `a`, `b` and `carry_in`, as wide as the wider of
+/// `a` and `b`, with the carry out when `carry_out` is set
+///
+/// The bits of the narrower operand past its end are taken as 0.
+fn ripple(
+    a: &[Bit],
+    b: &[Bit],
+    carry_in: Option<Bit>,
+    carry_out: bool,
+    variant: Variant,
+) -> (Vec<Bit>, Option<Bit>) {
+    let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut carry = carry_in;
+    let mut sum = Vec::with_capacity(a.len());
+    for (i, x) in a.iter().enumerate() {
+        let needed = carry_out || i + 1 < a.len();
+        let (bit, next) = match (b.get(i), &carry) {
+            (Some(y), None) => (x ^ y, needed.then(|| x & y)),
+            (Some(y), Some(c)) => (&(x ^ y) ^ c, needed.then(|| full_carry(x, y, c, variant))),
+            (None, Some(c)) => (x ^ c, needed.then(|| x & c)),
+            (None, None) => (x.clone(), None),
+        };
+        sum.push(bit);
+        carry = next;
+    }
+    (sum, carry.filter(|_| carry_out))
+}
+
+/// Carry out of `a + b` without the sum bits
+fn carry_chain(a: &[Bit], b: &[Bit], variant: Variant) -> Bit {
+    a.iter()
+        .zip(b)
+        .fold(None, |carry, (x, y)| {
+            Some(match carry {
+                None => x & y,
+                Some(carry) => full_carry(x, y, &carry, variant),
+            })
+        })
+        .expect("integer without bits")
+}
+
+fn not_all(a: &[Bit]) -> Vec<Bit> {
+    a.iter().map(|bit| !bit).collect()
+}
+
+fn assert_same_width(a: &[Bit], b: &[Bit]) {
+    assert!(!a.is_empty(), "integer without bits");
+    assert_eq!(a.len(), b.len(), "integers of different widths");
+}
+
+/// `a + b` modulo `2^n` with a ripple-carry adder, and the carry out
+///
+/// Takes one AND gate per bit, or three AND-like gates for the textbook
+/// carry `(a & b) | (c & (a ^ b))`.
+pub fn ripple_add(a: &[Bit], b: &[Bit], variant: Variant) -> (Vec<Bit>, Bit) {
+    assert_same_width(a, b);
+    let (sum, carry) = ripple(a, b, None, true, variant);
+    (sum, carry.unwrap())
+}
+
+/// `a + b` modulo `2^n` with a Sklansky carry-lookahead adder, and the carry
+/// out
+///
+/// The carries take `log2(n)` levels of AND gates instead of `n`, at the
+/// cost of about `n log2(n) / 2` more. The optimized variant merges group
+/// generate bits with XOR, as a group cannot both generate and propagate.
+pub fn lookahead_add(a: &[Bit], b: &[Bit], variant: Variant) -> (Vec<Bit>, Bit) {
+    assert_same_width(a, b);
+    let (sum, carry) = lookahead(a, b, true, variant);
+    (sum, carry.unwrap())
+}
+
+fn lookahead(a: &[Bit], b: &[Bit], carry_out: bool, variant: Variant) -> (Vec<Bit>, Option<Bit>) {
+    let n = a.len();
+    let propagate_bits: Vec<Bit> = a.iter().zip(b).map(|(x, y)| x ^ y).collect();
+    // Carries out of the first `count` bits are needed
+    let count = if carry_out { n } else { n - 1 };
+    let mut generate: Vec<Bit> = a.iter().zip(b).take(count).map(|(x, y)| x & y).collect();
+    let mut propagate = propagate_bits[..count].to_vec();
+
+    // At each level, a node in the upper half of a block absorbs the node
+    // ending its lower half, after which it covers the block up to itself
+    let mut level = 0;
+    while 1 << level < count {
+        for i in (0..count).filter(|i| (i >> level) & 1 == 1) {
+            let j = ((i >> level) << level) - 1;
+            let absorbed = &propagate[i] & &generate[j];
+            generate[i] = match variant {
+                Variant::Textbook => &generate[i] | &absorbed,
+                Variant::AndOptimized => &generate[i] ^ &absorbed,
+            };
+            // Nodes covering everything down to bit 0 are not absorbed later
+            if (i >> (level + 1)) << (level + 1) > 0 {
+                propagate[i] = &propagate[i] & &propagate[j];
+            }
+        }
+        level += 1;
+    }
+
+    let mut sum = vec![propagate_bits[0].clone()];
+    sum.extend((1..n).map(|i| &propagate_bits[i] ^ &generate[i - 1]));
+    (sum, generate.get(n - 1).filter(|_| carry_out).cloned())
+}
+
+/// `a - b` modulo `2^n`, and the borrow out, which is 1 when `a < b`
+///
+/// Computed as `!(!a + b)` with a ripple-carry adder.
+pub fn subtract(a: &[Bit], b: &[Bit], variant: Variant) -> (Vec<Bit>, Bit) {
+    assert_same_width(a, b);
+    let (sum, carry) = ripple(&not_all(a), b, None, true, variant);
+    (not_all(&sum), carry.unwrap())
+}
+
+pub(crate) fn add_wrapping(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    ripple(a, b, None, false, variant).0
+}
+
+pub(crate) fn subtract_wrapping(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    not_all(&ripple(&not_all(a), b, None, false, variant).0)
+}
+
+/// `-a` modulo `2^n`, as `!a + 1`
+pub fn negate(a: &[Bit]) -> Vec<Bit> {
+    assert!(!a.is_empty(), "integer without bits");
+    // The lowest bit keeps its value and carries !a[0]
+    let (high, _) = ripple(
+        &not_all(&a[1..]),
+        &[],
+        Some(!&a[0]),
+        false,
+        Variant::AndOptimized,
+    );
+    [vec![a[0].clone()], high].concat()
+}
+
+/// `-a` when `sign` is 1 and `a` otherwise, as `(a ^ sign) + sign`
+fn negate_if(a: &[Bit], sign: &Bit) -> Vec<Bit> {
+    let flipped: Vec<Bit> = a.iter().map(|bit| bit ^ sign).collect();
+    ripple(
+        &flipped,
+        &[],
+        Some(sign.clone()),
+        false,
+        Variant::AndOptimized,
+    )
+    .0
+}
+
+/// Absolute value of a two's-complement integer, modulo `2^n`
+pub fn abs(a: &[Bit]) -> Vec<Bit> {
+    negate_if(a, a.last().expect("integer without bits"))
+}
+
+/// Product of the low `width` bits, from rows of partial products added
+/// with ripple-carry adders
+fn product(a: &[Bit], b: &[Bit], width: usize, variant: Variant) -> Vec<Bit> {
+    let row = |j: usize| -> Vec<Bit> { a.iter().take(width - j).map(|bit| bit & &b[j]).collect() };
+    let mut product = row(0);
+    for j in 1..b.len().min(width) {
+        let high = product.split_off(j);
+        let row = row(j);
+        let carry_out = j + row.len().max(high.len()) < width;
+        let (sum, carry) = ripple(&row, &high, None, carry_out, variant);
+        product.extend(sum);
+        product.extend(carry);
+    }
+    while product.len() < width {
+        product.push(zero(&a[0]));
+    }
+    product.truncate(width);
+    product
+}
+
+/// Full product `a * b` of unsigned integers, `a.len() + b.len()` bits wide
+///
+/// Takes `a.len() * b.len()` AND gates for the partial products plus those
+/// of the adders summing them.
+pub fn multiply(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    assert!(!a.is_empty() && !b.is_empty(), "integer without bits");
+    product(a, b, a.len() + b.len(), variant)
+}
+
+pub(crate) fn multiply_wrapping(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    product(a, b, a.len(), variant)
+}
+
+/// Full product `a * b` of two's-complement integers, `a.len() + b.len()`
+/// bits wide
+pub fn signed_multiply(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    assert!(!a.is_empty() && !b.is_empty(), "integer without bits");
+    let width = a.len() + b.len();
+    let extend = |x: &[Bit]| -> Vec<Bit> {
+        let sign = x.last().unwrap();
+        x.iter()
+            .chain(std::iter::repeat(sign))
+            .take(width)
+            .cloned()
+            .collect()
+    };
+    product(&extend(a), &extend(b), width, variant)
+}
+
+/// Quotient and remainder of unsigned `a / b`, as wide as `a` and `b`
+///
+/// The textbook variant is restoring division, which subtracts `b` at every
+/// step and keeps the difference only when it is not negative. The optimized
+/// variant is non-restoring division, which adds or subtracts `b` depending
+/// on the sign of the running remainder and so needs no multiplexers.
+/// Division by zero gives unspecified bits.
+pub fn divide(a: &[Bit], b: &[Bit], variant: Variant) -> (Vec<Bit>, Vec<Bit>) {
+    assert!(!a.is_empty() && !b.is_empty(), "integer without bits");
+    let (mut quotient, remainder) = match variant {
+        Variant::Textbook => restoring_divide(a, b),
+        Variant::AndOptimized => nonrestoring_divide(a, b),
+    };
+    quotient.reverse();
+    (quotient, remainder)
+}
+
+/// Quotient bits from the most significant one, and the remainder
+fn restoring_divide(a: &[Bit], b: &[Bit]) -> (Vec<Bit>, Vec<Bit>) {
+    let n = b.len();
+    let divisor: Vec<Bit> = b.iter().cloned().chain([zero(&b[0])]).collect();
+    let mut remainder: Vec<Bit> = (0..n).map(|_| zero(&b[0])).collect();
+    let mut quotient = Vec::with_capacity(a.len());
+    for bit in a.iter().rev() {
+        let shifted: Vec<Bit> = [bit.clone()].into_iter().chain(remainder).collect();
+        let (difference, negative) = subtract(&shifted, &divisor, Variant::Textbook);
+        remainder = (0..n)
+            .map(|i| mux(&negative, &shifted[i], &difference[i], Variant::Textbook))
+            .collect();
+        quotient.push(!negative);
+    }
+    (quotient, remainder)
+}
+
+/// Quotient bits from the most significant one, and the remainder
+fn nonrestoring_divide(a: &[Bit], b: &[Bit]) -> (Vec<Bit>, Vec<Bit>) {
+    // The running remainder lies in [-b, b), so doubling it needs n + 2 bits
+    let width = b.len() + 2;
+    let mut remainder: Vec<Bit> = (0..width).map(|_| zero(&b[0])).collect();
+    let mut quotient = Vec::with_capacity(a.len());
+    for bit in a.iter().rev() {
+        // Subtract b, as + !b + 1, unless the remainder is negative
+        let subtracting = !&remainder[width - 1];
+        let shifted: Vec<Bit> = [bit.clone()]
+            .into_iter()
+            .chain(remainder[..width - 1].iter().cloned())
+            .collect();
+        let addend: Vec<Bit> = (0..width)
+            .map(|i| match b.get(i) {
+                Some(divisor) => divisor ^ &subtracting,
+                None => subtracting.clone(),
+            })
+            .collect();
+        remainder = ripple(
+            &shifted,
+            &addend,
+            Some(subtracting),
+            false,
+            Variant::AndOptimized,
+        )
+        .0;
+        quotient.push(!&remainder[width - 1]);
+    }
+
+    // A negative final remainder is corrected by adding b back
+    let negative = &remainder[width - 1];
+    let correction: Vec<Bit> = b.iter().map(|bit| bit & negative).collect();
+    let remainder = add_wrapping(&remainder[..b.len()], &correction, Variant::AndOptimized);
+    (quotient, remainder)
+}
+
+/// `a mod modulus` for unsigned integers, as wide as `modulus`
+///
+/// The remainder of `divide`, so a zero modulus gives unspecified bits.
+pub fn reduce(a: &[Bit], modulus: &[Bit], variant: Variant) -> Vec<Bit> {
+    divide(a, modulus, variant).1
+}
+
+/// Quotient and remainder of two's-complement `a / b`, rounding towards
+/// zero like Rust's `wrapping_div` and `wrapping_rem`
+pub fn signed_divide(a: &[Bit], b: &[Bit], variant: Variant) -> (Vec<Bit>, Vec<Bit>) {
+    assert_same_width(a, b);
+    let (sign_a, sign_b) = (a.last().unwrap(), b.last().unwrap());
+    let (quotient, remainder) = divide(&abs(a), &abs(b), variant);
+    (
+        negate_if(&quotient, &(sign_a ^ sign_b)),
+        negate_if(&remainder, sign_a),
+    )
+}
+
+/// 1 when unsigned `a < b`, the borrow out of `a - b`
+pub fn less_than(a: &[Bit], b: &[Bit], variant: Variant) -> Bit {
+    assert_same_width(a, b);
+    carry_chain(&not_all(a), b, variant)
+}
+
+/// 1 when unsigned `a <= b`
+pub fn less_equal(a: &[Bit], b: &[Bit], variant: Variant) -> Bit {
+    !less_than(b, a, variant)
+}
+
+/// 1 when `a == b`, with `n - 1` AND gates
+pub fn equal(a: &[Bit], b: &[Bit]) -> Bit {
+    assert_same_width(a, b);
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| !(x ^ y))
+        .reduce(|all, equal| all & equal)
+        .unwrap()
+}
+
+/// `if_true` when `select` is 1 and `if_false` otherwise, bit by bit
+pub fn select(select: &Bit, if_true: &[Bit], if_false: &[Bit], variant: Variant) -> Vec<Bit> {
+    assert_same_width(if_true, if_false);
+    if_true
+        .iter()
+        .zip(if_false)
+        .map(|(x, y)| mux(select, x, y, variant))
+        .collect()
+}
+
+/// The larger of unsigned `a` and `b`
+pub fn max(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    select(&less_than(a, b, variant), b, a, variant)
+}
+
+/// The smaller of unsigned `a` and `b`
+pub fn min(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    select(&less_than(a, b, variant), a, b, variant)
+}
+
+/// Two's-complement integer as the unsigned integer of the same order,
+/// which flips its sign bit
+fn biased(a: &[Bit]) -> Vec<Bit> {
+    let mut biased = a.to_vec();
+    if let Some(sign) = biased.last_mut() {
+        *sign = !&*sign;
+    }
+    biased
+}
+
+/// 1 when two's-complement `a < b`
+pub fn signed_less_than(a: &[Bit], b: &[Bit], variant: Variant) -> Bit {
+    less_than(&biased(a), &biased(b), variant)
+}
+
+/// 1 when two's-complement `a <= b`
+pub fn signed_less_equal(a: &[Bit], b: &[Bit], variant: Variant) -> Bit {
+    !signed_less_than(b, a, variant)
+}
+
+/// The larger of two's-complement `a` and `b`
+pub fn signed_max(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    select(&signed_less_than(a, b, variant), b, a, variant)
+}
+
+/// The smaller of two's-complement `a` and `b`
+pub fn signed_min(a: &[Bit], b: &[Bit], variant: Variant) -> Vec<Bit> {
+    select(&signed_less_than(a, b, variant), a, b, variant)
+}
+
+/// `a << shift` modulo `2^n`, which only rewires
+pub fn shift_left(a: &[Bit], shift: usize) -> Vec<Bit> {
+    let shift = shift.min(a.len());
+    let zeros = a[..shift].iter().map(zero);
+    zeros.chain(a[..a.len() - shift].iter().cloned()).collect()
+}
+
+/// Unsigned `a >> shift`, which only rewires
+pub fn shift_right(a: &[Bit], shift: usize) -> Vec<Bit> {
+    let shift = shift.min(a.len());
+    let zeros = a[..shift].iter().map(zero);
+    a[shift..].iter().cloned().chain(zeros).collect()
+}
+
+/// Two's-complement `a >> shift`, filling with the sign bit
+pub fn shift_right_arithmetic(a: &[Bit], shift: usize) -> Vec<Bit> {
+    let sign = a.last().expect("integer without bits");
+    let shift = shift.min(a.len());
+    let signs = std::iter::repeat_n(sign, shift);
+    a[shift..].iter().chain(signs).cloned().collect()
+}
+
+/// Barrel shifter applying `shift` to `a` by the amount `amount`, one
+/// multiplexer per bit for every bit of `amount`
+///
+/// Amounts of at least `n` give `fill`.
+fn barrel(
+    a: &[Bit],
+    amount: &[Bit],
+    shift: impl Fn(&[Bit], usize) -> Vec<Bit>,
+    fill: &Bit,
+    variant: Variant,
+) -> Vec<Bit> {
+    let mut value = a.to_vec();
+    let mut overflow: Option<Bit> = None;
+    for (k, bit) in amount.iter().enumerate() {
+        if k < usize::BITS as usize && 1 << k < a.len() {
+            value = select(bit, &shift(&value, 1 << k), &value, variant);
+        } else {
+            overflow = Some(match overflow {
+                None => bit.clone(),
+                Some(overflow) => &overflow | bit,
+            });
+        }
+    }
+    match overflow {
+        Some(overflow) => value
+            .iter()
+            .map(|bit| mux(&overflow, fill, bit, variant))
+            .collect(),
+        None => value,
+    }
+}
+
+/// `a << amount` modulo `2^n` for an unsigned integer `amount`
+pub fn shift_left_by(a: &[Bit], amount: &[Bit], variant: Variant) -> Vec<Bit> {
+    assert!(!a.is_empty(), "integer without bits");
+    barrel(a, amount, shift_left, &zero(&a[0]), variant)
+}
+
+/// Unsigned `a >> amount` for an unsigned integer `amount`
+pub fn shift_right_by(a: &[Bit], amount: &[Bit], variant: Variant) -> Vec<Bit> {
+    assert!(!a.is_empty(), "integer without bits");
+    barrel(a, amount, shift_right, &zero(&a[0]), variant)
+}
+
+/// Two's-complement `a >> amount` for an unsigned integer `amount`
+pub fn shift_right_arithmetic_by(a: &[Bit], amount: &[Bit], variant: Variant) -> Vec<Bit> {
+    let sign = a.last().expect("integer without bits");
+    barrel(a, amount, shift_right_arithmetic, sign, variant)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::CircuitBuilder;
+    use crate::test_support::evaluate;
+
+    const WIDTH: usize = 4;
+    const VARIANTS: [Variant; 2] = [Variant::Textbook, Variant::AndOptimized];
+
+    /// Check a gadget over one or two 4-bit operands, Alice's and Bob's,
+    /// against `expected` on every input, the operands packed into one
+    /// integer and the outputs read as one; returns its AND-like gates
+    fn run(
+        operands: usize,
+        gadget: impl Fn(&[Bit]) -> Vec<Bit>,
+        expected: impl Fn(u32) -> Option<u32>,
+    ) -> usize {
+        let builder = CircuitBuilder::new();
+        let mut inputs: Vec<Bit> = (0..WIDTH).map(|_| builder.alice_bit()).collect();
+        if operands == 2 {
+            inputs.extend((0..WIDTH).map(|_| builder.bob_bit()));
+        }
+        for bit in gadget(&inputs) {
+            builder.output(&bit);
+        }
+        let circuit = builder.build("gadget").unwrap();
+
+        for input in 0..1u32 << inputs.len() {
+            let Some(expected) = expected(input) else {
+                continue;
+            };
+            let bits: Vec<u8> = (0..inputs.len())
+                .map(|i| ((input >> i) & 1) as u8)
+                .collect();
+            let outputs = evaluate(&circuit, &bits);
+            let value = outputs
+                .iter()
+                .enumerate()
+                .fold(0, |value, (i, &bit)| value | (bit as u32) << i);
+            let mask = (1u64 << outputs.len()) - 1;
+            assert_eq!(value as u64, expected as u64 & mask, "input {input:#x}");
+        }
+        circuit
+            .gates
+            .iter()
+            .filter(|gate| gate.gate_type.and_form().is_some())
+            .count()
+    }
+
+    fn check(
+        gadget: impl Fn(&[Bit], &[Bit]) -> Vec<Bit>,
+        expected: impl Fn(u32, u32) -> Option<u32>,
+    ) -> usize {
+        let mask = (1 << WIDTH) - 1;
+        run(
+            2,
+            |bits| gadget(&bits[..WIDTH], &bits[WIDTH..]),
+            |input| expected(input & mask, input >> WIDTH),
+        )
+    }
+
+    fn check_unary(gadget: impl Fn(&[Bit]) -> Vec<Bit>, expected: impl Fn(u32) -> u32) {
+        run(1, gadget, |input| Some(expected(input)));
+    }
+
+    /// 4-bit two's-complement value
+    fn signed(value: u32) -> i32 {
+        (value as i32) << 28 >> 28
+    }
+
+    #[test]
+    fn test_adders() {
+        for variant in VARIANTS {
+            let ripple = |a: &[Bit], b: &[Bit]| {
+                let (sum, carry) = ripple_add(a, b, variant);
+                [sum, vec![carry]].concat()
+            };
+            let lookahead = |a: &[Bit], b: &[Bit]| {
+                let (sum, carry) = lookahead_add(a, b, variant);
+                [sum, vec![carry]].concat()
+            };
+            let sum = |a, b| Some(a + b);
+            let ands = (check(ripple, sum), check(lookahead, sum));
+            let expected = match variant {
+                Variant::Textbook => (10, 13),
+                Variant::AndOptimized => (4, 9),
+            };
+            assert_eq!(ands, expected, "{variant:?}");
+
+            let difference = |a: &[Bit], b: &[Bit]| {
+                let (difference, borrow) = subtract(a, b, variant);
+                [difference, vec![borrow]].concat()
+            };
+            check(difference, |a, b| Some(a.wrapping_sub(b) & 31));
+        }
+        check_unary(negate, |a| a.wrapping_neg());
+        check_unary(abs, |a| signed(a).unsigned_abs());
+    }
+
+    #[test]
+    fn test_multiply() {
+        for variant in VARIANTS {
+            let ands = check(|a, b| multiply(a, b, variant), |a, b| Some(a * b));
+            let signed_product = |a, b| Some((signed(a) * signed(b)) as u32);
+            check(|a, b| signed_multiply(a, b, variant), signed_product);
+            let wrapping = |a, b| Some(a * b);
+            check(|a, b| multiply_wrapping(a, b, variant), wrapping);
+            let expected = match variant {
+                Variant::Textbook => 16 + 8 + 10 + 10,
+                Variant::AndOptimized => 16 + 4 + 4 + 4,
+            };
+            assert_eq!(ands, expected, "{variant:?}");
+        }
+    }
+
+    #[test]
+    fn test_divide() {
+        let mut ands = Vec::new();
+        for variant in VARIANTS {
+            let quotient = |a, b| (b != 0).then(|| a / b);
+            let remainder = |a, b| (b != 0).then(|| a % b);
+            ands.push(check(|a, b| divide(a, b, variant).0, quotient));
+            check(|a, b| reduce(a, b, variant), remainder);
+
+            let signed_quotient = |a, b| (b != 0).then(|| signed(a).wrapping_div(signed(b)) as u32);
+            let signed_remainder =
+                |a, b| (b != 0).then(|| signed(a).wrapping_rem(signed(b)) as u32);
+            check(|a, b| signed_divide(a, b, variant).0, signed_quotient);
+            check(|a, b| signed_divide(a, b, variant).1, signed_remainder);
+        }
+        assert!(ands[1] < ands[0] / 2, "{ands:?}");
+    }
+
+    #[test]
+    fn test_comparisons() {
+        for variant in VARIANTS {
+            let bit = |bit: Bit| vec![bit];
+            check(
+                |a, b| bit(less_than(a, b, variant)),
+                |a, b| Some((a < b) as u32),
+            );
+            check(
+                |a, b| bit(less_equal(a, b, variant)),
+                |a, b| Some((a <= b) as u32),
+            );
+            check(|a, b| max(a, b, variant), |a, b| Some(a.max(b)));
+            check(|a, b| min(a, b, variant), |a, b| Some(a.min(b)));
+
+            let less = |a, b| Some((signed(a) < signed(b)) as u32);
+            let less_or_equal = |a, b| Some((signed(a) <= signed(b)) as u32);
+            check(|a, b| bit(signed_less_than(a, b, variant)), less);
+            check(|a, b| bit(signed_less_equal(a, b, variant)), less_or_equal);
+            let largest = |a, b| Some(if signed(a) < signed(b) { b } else { a });
+            let smallest = |a, b| Some(if signed(a) < signed(b) { a } else { b });
+            check(|a, b| signed_max(a, b, variant), largest);
+            check(|a, b| signed_min(a, b, variant), smallest);
+        }
+        let ands = check(|a, b| vec![equal(a, b)], |a, b| Some((a == b) as u32));
+        assert_eq!(ands, WIDTH - 1);
+
+        // One AND gate per bit against the textbook three
+        let counts = VARIANTS.map(|variant| {
+            check(
+                |a, b| vec![less_than(a, b, variant)],
+                |a, b| Some((a < b) as u32),
+            )
+        });
+        assert_eq!(counts, [3 * WIDTH - 2, WIDTH]);
+    }
+
+    #[test]
+    fn test_shifts() {
+        // Constant shifts drop bits, so the operand is output as well to use
+        // every input
+        let mask = (1 << WIDTH) - 1;
+        for shift in 0..=WIDTH + 1 {
+            let with_operand = |shifted: Vec<Bit>, a: &[Bit]| [shifted, a.to_vec()].concat();
+            let left = |a| (a << shift) & mask | a << WIDTH;
+            check_unary(|a| with_operand(shift_left(a, shift), a), left);
+            let right = |a| a >> shift | a << WIDTH;
+            check_unary(|a| with_operand(shift_right(a, shift), a), right);
+            let arithmetic = |a| (signed(a) >> shift) as u32 & mask | a << WIDTH;
+            let shifted = |a: &[Bit]| with_operand(shift_right_arithmetic(a, shift), a);
+            check_unary(shifted, arithmetic);
+        }
+        for variant in VARIANTS {
+            let left = |a: u32, b| Some(a.checked_shl(b).unwrap_or(0));
+            let right = |a: u32, b| Some(a.checked_shr(b).unwrap_or(0));
+            let arithmetic = |a, b: u32| Some((signed(a) >> b.min(31)) as u32);
+            check(|a, b| shift_left_by(a, b, variant), left);
+            check(|a, b| shift_right_by(a, b, variant), right);
+            check(|a, b| shift_right_arithmetic_by(a, b, variant), arithmetic);
+        }
+    }
+}
@@ -1,292 +1,1181 @@
-use crate::circuit::{Circuit, Gate};
+use crate::circuit::{Circuit, CircuitError, Gate, GateType};
+use crate::error::{Error, Result};
 use crate::key::Key;
+use rand::Rng;
 use std::collections::HashMap;
 
-// AES key size in bytes
-const AES_KEY_SIZE: usize = 16;
+/// How wire labels are chosen and which gates need a garbled table
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GarblingScheme {
+    /// Two independent labels per wire and a full table for every gate
+    Classic,
+    /// Independent labels as in `Classic`, with one output label derived from
+    /// the row hashes so the first row of every table is implicitly zero
+    Grr3,
+    /// One global offset `delta` with `key1 = key0 ^ delta` on every wire,
+    /// so XOR, XNOR, NOT, BUF and the projections are evaluated without any
+    /// table
+    #[default]
+    FreeXor,
+    /// Free XOR plus the half-gates construction of Zahur, Rosulek and Evans,
+    /// so AND-like gates cost two ciphertexts each
+    HalfGates,
+    /// Free XOR plus the "three halves make a whole" construction of Rosulek
+    /// and Roy, so AND-like gates cost three half-size ciphertexts and
+    /// sixteen control bits each
+    ThreeHalves,
+}
+
+impl GarblingScheme {
+    /// Every supported scheme
+    pub const ALL: [GarblingScheme; 5] = [
+        GarblingScheme::Classic,
+        GarblingScheme::Grr3,
+        GarblingScheme::FreeXor,
+        GarblingScheme::HalfGates,
+        GarblingScheme::ThreeHalves,
+    ];
+
+    /// Whether wire labels are correlated by a global offset
+    pub fn uses_delta(&self) -> bool {
+        !matches!(self, GarblingScheme::Classic | GarblingScheme::Grr3)
+    }
+
+    /// Whether this gate type needs no table, either because it is evaluated
+    /// by XORing labels or because it is a constant whose label is public
+    pub fn is_free(&self, gate_type: GateType) -> bool {
+        gate_type.arity() == Some(0) || (self.uses_delta() && gate_type.affine_form().is_some())
+    }
+}
+
+/// Tweak bit separating output decoding hashes from gate hashes
+const OUTPUT_TWEAK: u64 = 1 << 63;
+
+/// Hash of an output label published for decoding, tweaked by the wire id
+fn output_hash(key: &Key, wire: u32) -> Key {
+    key.hash(OUTPUT_TWEAK | wire as u64)
+}
+
+/// Table row selected by the permute bits of the given input labels
+fn row_index(keys: &[&Key]) -> usize {
+    keys.iter()
+        .fold(0, |index, key| (index << 1) | key.permute_bit() as usize)
+}
+
+/// Hash tweak of slot `slot` of a gate
+///
+/// Every gate owns the four tweaks sharing its id in the upper bits, so no
+/// two hashes of a run share a tweak whatever kind of gate makes them.
+fn gate_tweak(gate_id: u32, slot: u64) -> u64 {
+    debug_assert!(slot < 4);
+    (gate_id as u64) << 2 | slot
+}
+
+/// Tweak of the row hashes of a table or LUT gate
+fn table_tweak(gate_id: u32) -> u64 {
+    gate_tweak(gate_id, 0)
+}
+
+/// Mask of the row opened by the given input labels, one hash over all of
+/// them tweaked by the gate id
+fn row_mask(keys: &[&Key], gate_id: u32) -> Key {
+    Key::hash_many(keys, table_tweak(gate_id))
+}
+
+/// Label of a pair encoding `bit`
+fn select_key(pair: &(Key, Key), bit: u8) -> &Key {
+    if bit == 0 {
+        &pair.0
+    } else {
+        &pair.1
+    }
+}
+
+/// Every assignment of bits to `arity` inputs
+fn input_combinations(arity: usize) -> impl Iterator<Item = Vec<u8>> {
+    (0..1usize << arity).map(move |combination| {
+        (0..arity)
+            .map(|position| ((combination >> position) & 1) as u8)
+            .collect()
+    })
+}
+
+/// Hash tweaks of the garbler and evaluator halves of a gate
+fn half_gate_tweaks(gate_id: u32) -> (u64, u64) {
+    (gate_tweak(gate_id, 1), gate_tweak(gate_id, 2))
+}
+
+/// Hash tweaks of the `A`, `B` and `A ^ B` hashes of a three-halves gate
+fn three_halves_tweaks(gate_id: u32) -> [u64; 3] {
+    [1, 2, 3].map(|slot| gate_tweak(gate_id, slot))
+}
+
+/// Left and right halves of a label; the permute bit sits in the left half
+fn split_halves(key: &Key) -> (u64, u64) {
+    let left = u64::from_le_bytes(key.0[..8].try_into().unwrap());
+    let right = u64::from_le_bytes(key.0[8..].try_into().unwrap());
+    (left, right)
+}
+
+fn join_halves(left: u64, right: u64) -> Key {
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&left.to_le_bytes());
+    key[8..].copy_from_slice(&right.to_le_bytes());
+    Key(key)
+}
+
+/// Half-size hash of a label together with four bits masking the control bits
+fn half_hash(key: &Key, tweak: u64) -> (u64, u8) {
+    let hash = key.hash(tweak);
+    (split_halves(&hash).0, hash.0[8] & 0xf)
+}
+
+/// Combination of the halves of a label selected by a two-bit coefficient
+/// vector: bit 0 picks the left half, bit 1 the right half
+fn dot(coefficients: u8, key: &Key) -> u64 {
+    let (left, right) = split_halves(key);
+    let pick = |bit: u8, half: u64| if bit & 1 == 1 { half } else { 0 };
+    pick(coefficients, left) ^ pick(coefficients >> 1, right)
+}
+
+/// Linear map of a three-halves row applied to the input labels
+///
+/// The map for row `(i, j)` is `[0, x ^ (i, 0); x ^ (0, j), y]` over the
+/// halves of `a` and `b`, where `x` and `y` are the decrypted control bits.
+fn three_halves_map(i: u8, j: u8, x: u8, y: u8, key_a: &Key, key_b: &Key) -> Key {
+    let left = dot(x ^ i, key_b);
+    let right = dot(x ^ (j << 1), key_a) ^ dot(y, key_b);
+    join_halves(left, right)
+}
 
 #[derive(Clone, Debug)]
 pub struct GarbledGate {
     pub id: u32,
-    pub gate_type: String,
+    pub gate_type: GateType,
     pub inputs: Vec<u32>,
-    pub garbled_table: HashMap<Vec<u8>, Vec<u8>>,
+    /// Encrypted rows, permuted by the permute bits of the input labels
+    pub garbled_table: Vec<Vec<u8>>,
 }
 
 impl GarbledGate {
-    pub fn new(gate: &Gate, keys: &HashMap<u32, (Key, Key)>) -> Self {
+    fn new(gate: &Gate, keys: &HashMap<u32, (Key, Key)>, scheme: GarblingScheme) -> Self {
         let mut garbled_gate = GarbledGate {
             id: gate.id,
-            gate_type: gate.gate_type.clone(),
+            gate_type: gate.gate_type,
             inputs: gate.inputs.clone(),
-            garbled_table: HashMap::new(),
+            garbled_table: Vec::new(),
         };
 
-        match gate.gate_type.as_str() {
-            "AND" => garbled_gate.create_and_table(keys),
-            "OR" => garbled_gate.create_or_table(keys),
-            "XOR" => garbled_gate.create_xor_table(keys),
-            "NOT" => garbled_gate.create_not_table(keys),
-            _ => panic!("Unsupported gate type: {}", gate.gate_type),
+        // Free gates carry no table, the evaluator combines the input labels directly
+        if scheme.is_free(gate.gate_type) {
+            return garbled_gate;
         }
 
+        garbled_gate.create_table(gate, keys);
         garbled_gate
     }
 
-    fn create_binary_gate_table<F>(&mut self, keys: &HashMap<u32, (Key, Key)>, truth_table: F)
-    where
-        F: Fn(u8, u8) -> u8,
-    {
-        let in_a = self.inputs[0];
-        let in_b = self.inputs[1];
-        let output_keys = keys.get(&self.id).unwrap();
+    /// Garble an AND-like gate with two half gates, returning the gate and
+    /// the output 0-label the half gates determine
+    ///
+    /// The negations of `and_form` are folded into the labels, so the
+    /// evaluator runs the same procedure for every AND-like gate type.
+    fn new_half_gate(gate: &Gate, keys: &HashMap<u32, (Key, Key)>, delta: &Key) -> (Self, Key) {
+        let (alpha, beta, gamma) = gate
+            .gate_type
+            .and_form()
+            .unwrap_or_else(|| panic!("Not an AND-like gate: {:?}", gate.gate_type));
+        let select = |bit: u8, key: &Key| if bit == 0 { Key::zero() } else { key.clone() };
 
-        for a_bit in 0..2 {
-            for b_bit in 0..2 {
-                let result_bit = truth_table(a_bit, b_bit);
+        let a0 = &keys[&gate.inputs[0]].0 ^ &select(alpha, delta);
+        let b0 = &keys[&gate.inputs[1]].0 ^ &select(beta, delta);
+        let (a1, b1) = (&a0 ^ delta, &b0 ^ delta);
+        let (tweak_g, tweak_e) = half_gate_tweaks(gate.id);
 
-                let key_a = if a_bit == 0 {
-                    &keys[&in_a].0
-                } else {
-                    &keys[&in_a].1
-                };
-                let key_b = if b_bit == 0 {
-                    &keys[&in_b].0
-                } else {
-                    &keys[&in_b].1
-                };
-                let output_key = if result_bit == 0 {
-                    &output_keys.0
-                } else {
-                    &output_keys.1
-                };
+        // Garbler half gate: the garbler knows the permute bit of b
+        let (ha0, ha1) = (a0.hash(tweak_g), a1.hash(tweak_g));
+        let table_g = &(&ha0 ^ &ha1) ^ &select(b0.permute_bit(), delta);
+        let wire_g = &ha0 ^ &select(a0.permute_bit(), &table_g);
 
-                // Encrypt output key with both input keys using magic bytes
-                let encrypted_once = key_a.encrypt_with_magic(&output_key.0);
-                let encrypted_twice = key_b.encrypt_with_magic(&encrypted_once);
+        // Evaluator half gate: the evaluator knows the permute bit of b
+        let (hb0, hb1) = (b0.hash(tweak_e), b1.hash(tweak_e));
+        let table_e = &(&hb0 ^ &hb1) ^ &a0;
+        let wire_e = &hb0 ^ &select(b0.permute_bit(), &(&table_e ^ &a0));
+
+        let output0 = &(&wire_g ^ &wire_e) ^ &select(gamma, delta);
+        let garbled_gate = GarbledGate {
+            id: gate.id,
+            gate_type: gate.gate_type,
+            inputs: gate.inputs.clone(),
+            garbled_table: vec![table_g.0.to_vec(), table_e.0.to_vec()],
+        };
+        (garbled_gate, output0)
+    }
+
+    /// Evaluate a half-gates table on the labels of the two inputs
+    fn evaluate_half_gate(&self, key_a: &Key, key_b: &Key) -> Result<Key> {
+        let (tweak_g, tweak_e) = half_gate_tweaks(self.id);
+        let table_g = Key(self.table_bytes(0)?);
+        let table_e = Key(self.table_bytes(1)?);
+
+        let mut wire_g = key_a.hash(tweak_g);
+        if key_a.permute_bit() == 1 {
+            wire_g = &wire_g ^ &table_g;
+        }
+        let mut wire_e = key_b.hash(tweak_e);
+        if key_b.permute_bit() == 1 {
+            wire_e = &wire_e ^ &(&table_e ^ key_a);
+        }
+        Ok(&wire_g ^ &wire_e)
+    }
 
-                let index = vec![a_bit, b_bit];
-                self.garbled_table.insert(index, encrypted_twice);
+    /// Garble an AND-like gate with the three-halves construction, returning
+    /// the gate and the output 0-label it determines
+    ///
+    /// The table holds three half-size ciphertexts `G0`, `G1`, `G2` followed
+    /// by four control bits per row, each row's bits masked by hashes only the
+    /// labels of that row can compute. Negations are folded into the labels
+    /// as with half gates.
+    fn new_three_halves(gate: &Gate, keys: &HashMap<u32, (Key, Key)>, delta: &Key) -> (Self, Key) {
+        let (alpha, beta, gamma) = gate
+            .gate_type
+            .and_form()
+            .unwrap_or_else(|| panic!("Not an AND-like gate: {:?}", gate.gate_type));
+        let select = |bit: u8, key: &Key| if bit == 0 { Key::zero() } else { key.clone() };
+
+        let a0 = &keys[&gate.inputs[0]].0 ^ &select(alpha, delta);
+        let b0 = &keys[&gate.inputs[1]].0 ^ &select(beta, delta);
+        let (pa, pb) = (a0.permute_bit(), b0.permute_bit());
+        let permute = pa | (pb << 1);
+
+        // Labels with permute bit 0, the ones the evaluator sees in row (0, 0)
+        let a = &a0 ^ &select(pa, delta);
+        let b = &b0 ^ &select(pb, delta);
+        let ab = &a ^ &b;
+        let [tweak_a, tweak_b, tweak_ab] = three_halves_tweaks(gate.id);
+        let hash_a = [half_hash(&a, tweak_a), half_hash(&(&a ^ delta), tweak_a)];
+        let hash_b = [half_hash(&b, tweak_b), half_hash(&(&b ^ delta), tweak_b)];
+        let hash_ab = [
+            half_hash(&ab, tweak_ab),
+            half_hash(&(&ab ^ delta), tweak_ab),
+        ];
+
+        // Random control bits hide the permute bits from every single row
+        let mut rng = rand::thread_rng();
+        let (s1, s2) = (rng.gen::<u8>() & 3, rng.gen::<u8>() & 3);
+
+        // Corrections that make all four rows agree up to delta times the
+        // AND of the inputs
+        let corrections = [
+            dot(1 ^ permute, &b) ^ dot((pb * 3) ^ s1, delta),
+            dot(permute ^ 2, &a) ^ dot(permute, &b) ^ dot(((pa ^ pb) << 1) ^ s1 ^ s2, delta),
+            dot(permute, &b) ^ dot((pb << 1) ^ s1, delta),
+        ];
+        let ciphertexts = [
+            hash_a[0].0 ^ hash_a[1].0 ^ corrections[0],
+            hash_b[0].0 ^ hash_b[1].0 ^ corrections[1],
+            hash_ab[0].0 ^ hash_ab[1].0 ^ corrections[2],
+        ];
+
+        let mut control = 0u16;
+        for i in 0..2u8 {
+            for j in 0..2u8 {
+                let x = s1 ^ (j * permute);
+                let y = s2 ^ (i * permute);
+                let mask =
+                    hash_a[i as usize].1 ^ hash_b[j as usize].1 ^ hash_ab[(i ^ j) as usize].1;
+                control |= (((x | (y << 2)) ^ mask) as u16) << (4 * (2 * i + j));
             }
         }
+
+        let base = join_halves(hash_a[0].0 ^ hash_ab[0].0, hash_b[0].0 ^ hash_ab[0].0);
+        // Row (0, 0) holds the inputs whose permute bits are zero, so its
+        // output is the AND of those inputs' values pa and pb
+        let row0 = &base ^ &three_halves_map(0, 0, s1, s2, &a, &b);
+        let output0 = &row0 ^ &select((pa & pb) ^ gamma, delta);
+
+        let mut garbled_table: Vec<Vec<u8>> = ciphertexts
+            .iter()
+            .map(|ciphertext| ciphertext.to_le_bytes().to_vec())
+            .collect();
+        garbled_table.push(control.to_le_bytes().to_vec());
+        let garbled_gate = GarbledGate {
+            id: gate.id,
+            gate_type: gate.gate_type,
+            inputs: gate.inputs.clone(),
+            garbled_table,
+        };
+        (garbled_gate, output0)
     }
 
-    fn create_and_table(&mut self, keys: &HashMap<u32, (Key, Key)>) {
-        // Truth table for AND: 00->0, 01->0, 10->0, 11->1
-        self.create_binary_gate_table(keys, |a, b| if a == 1 && b == 1 { 1 } else { 0 });
+    /// Evaluate a three-halves table on the labels of the two inputs
+    fn evaluate_three_halves(&self, key_a: &Key, key_b: &Key) -> Result<Key> {
+        let [tweak_a, tweak_b, tweak_ab] = three_halves_tweaks(self.id);
+        let (i, j) = (key_a.permute_bit(), key_b.permute_bit());
+        let hash_a = half_hash(key_a, tweak_a);
+        let hash_b = half_hash(key_b, tweak_b);
+        let hash_ab = half_hash(&(key_a ^ key_b), tweak_ab);
+
+        let ciphertexts = [
+            u64::from_le_bytes(self.table_bytes(0)?),
+            u64::from_le_bytes(self.table_bytes(1)?),
+            u64::from_le_bytes(self.table_bytes(2)?),
+        ];
+        let control = u16::from_le_bytes(self.table_bytes(3)?);
+        let bits = ((control >> (4 * (2 * i + j))) as u8 & 0xf) ^ hash_a.1 ^ hash_b.1 ^ hash_ab.1;
+
+        // Row (i, j) adds G0 when i is set, G1 when j is set and G2 when they differ
+        let pick = |bit: u8, index: usize| if bit == 1 { ciphertexts[index] } else { 0 };
+        let left = hash_a.0 ^ hash_ab.0 ^ pick(i, 0) ^ pick(i ^ j, 2);
+        let right = hash_b.0 ^ hash_ab.0 ^ pick(j, 1) ^ pick(i ^ j, 2);
+
+        Ok(&join_halves(left, right) ^ &three_halves_map(i, j, bits & 3, bits >> 2, key_a, key_b))
     }
 
-    fn create_or_table(&mut self, keys: &HashMap<u32, (Key, Key)>) {
-        // Truth table for OR: 00->0, 01->1, 10->1, 11->1
-        self.create_binary_gate_table(keys, |a, b| if a == 1 || b == 1 { 1 } else { 0 });
+    /// Garble a gate with row reduction, returning the gate and both output
+    /// labels
+    ///
+    /// The output label of the row at index 0 is that row's mask, so its
+    /// ciphertext is zero and only the remaining rows are sent.
+    fn new_grr3(gate: &Gate, keys: &HashMap<u32, (Key, Key)>) -> (Self, (Key, Key)) {
+        let input_pairs: Vec<&(Key, Key)> = gate.inputs.iter().map(|wire| &keys[wire]).collect();
+
+        // The labels with permute bit 0 encode the bit their 1-label has
+        let first_bits: Vec<u8> = input_pairs
+            .iter()
+            .map(|pair| pair.0.permute_bit())
+            .collect();
+        let first_keys: Vec<&Key> = input_pairs
+            .iter()
+            .zip(&first_bits)
+            .map(|(pair, &bit)| select_key(pair, bit))
+            .collect();
+        let first_output = row_mask(&first_keys, gate.id);
+        let other_output = Key::new().with_permute_bit(1 - first_output.permute_bit());
+        let output_keys = if gate.eval(&first_bits) == 0 {
+            (first_output, other_output)
+        } else {
+            (other_output, first_output)
+        };
+
+        let mut garbled_table = vec![Vec::new(); (1 << gate.inputs.len()) - 1];
+        for bits in input_combinations(gate.inputs.len()) {
+            let row_keys: Vec<&Key> = input_pairs
+                .iter()
+                .zip(&bits)
+                .map(|(pair, &bit)| select_key(pair, bit))
+                .collect();
+            let index = row_index(&row_keys);
+            if index == 0 {
+                continue;
+            }
+            let output_key = select_key(&output_keys, gate.eval(&bits));
+            garbled_table[index - 1] = (&row_mask(&row_keys, gate.id) ^ output_key).0.to_vec();
+        }
+
+        let garbled_gate = GarbledGate {
+            id: gate.id,
+            gate_type: gate.gate_type,
+            inputs: gate.inputs.clone(),
+            garbled_table,
+        };
+        (garbled_gate, output_keys)
     }
 
-    fn create_xor_table(&mut self, keys: &HashMap<u32, (Key, Key)>) {
-        // Truth table for XOR: 00->0, 01->1, 10->1, 11->0
-        self.create_binary_gate_table(keys, |a, b| a ^ b);
+    /// Evaluate a row-reduced table on the labels of the inputs
+    fn evaluate_grr3(&self, input_keys: &[&Key]) -> Result<Key> {
+        let mask = row_mask(input_keys, self.id);
+        match row_index(input_keys) {
+            0 => Ok(mask),
+            index => Ok(&mask ^ &Key(self.table_bytes(index - 1)?)),
+        }
     }
 
-    fn create_not_table(&mut self, keys: &HashMap<u32, (Key, Key)>) {
-        let input_wire = self.inputs[0];
-        let output_keys = keys.get(&self.id).unwrap();
+    /// Fill one row per input combination with the output label masked by
+    /// the hash of that row's input labels
+    ///
+    /// This covers every gate type, LUT gates included, so any gate is opened
+    /// with a single row decryption.
+    fn create_table(&mut self, gate: &Gate, keys: &HashMap<u32, (Key, Key)>) {
+        let input_pairs: Vec<&(Key, Key)> = self.inputs.iter().map(|wire| &keys[wire]).collect();
+        let output_keys = &keys[&self.id];
+        self.garbled_table = vec![Vec::new(); 1 << self.inputs.len()];
 
-        // Truth table for NOT: 0->1, 1->0
-        for bit in 0..2 {
-            let result_bit = 1 - bit;
+        for bits in input_combinations(self.inputs.len()) {
+            let row_keys: Vec<&Key> = input_pairs
+                .iter()
+                .zip(&bits)
+                .map(|(pair, &bit)| select_key(pair, bit))
+                .collect();
+            let output_key = select_key(output_keys, gate.eval(&bits));
 
-            let input_key = if bit == 0 {
-                &keys[&input_wire].0
-            } else {
-                &keys[&input_wire].1
-            };
-            let output_key = if result_bit == 0 {
-                &output_keys.0
-            } else {
-                &output_keys.1
-            };
+            // Rows are placed by permute bits, hiding the plaintext input bits
+            let index = row_index(&row_keys);
+            self.garbled_table[index] = (&row_mask(&row_keys, self.id) ^ output_key).0.to_vec();
+        }
+    }
 
-            let encrypted = input_key.encrypt_with_magic(&output_key.0);
+    /// Evaluate a full table on the labels of the inputs
+    fn evaluate_table(&self, input_keys: &[&Key]) -> Result<Key> {
+        let row = Key(self.table_bytes(row_index(input_keys))?);
+        Ok(&row_mask(input_keys, self.id) ^ &row)
+    }
 
-            let index = vec![bit];
-            self.garbled_table.insert(index, encrypted);
-        }
+    /// First `N` bytes of a table row, failing on a truncated table
+    fn table_bytes<const N: usize>(&self, index: usize) -> Result<[u8; N]> {
+        self.garbled_table
+            .get(index)
+            .and_then(|row| row.get(..N))
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(Error::Decryption("garbled table is truncated"))
     }
 }
 
+/// Evaluator's view of a garbled circuit: topology, tables and public
+/// decoding data, but no secret wire labels
 #[derive(Clone, Debug)]
 pub struct GarbledCircuit {
     pub circuit: Circuit,
-    pub keys: HashMap<u32, (Key, Key)>,
+    pub scheme: GarblingScheme,
     pub garbled_gates: Vec<GarbledGate>,
+    /// Labels of the constant wires and ZERO/ONE gates for their public
+    /// values, handed to the evaluator as they reveal nothing
+    pub constant_keys: HashMap<u32, Key>,
+    /// Hashes of the 0- and 1-label of every output wire, enough to decode an
+    /// output label without knowing the labels themselves
+    pub output_decoding: HashMap<u32, (Key, Key)>,
 }
 
-impl GarbledCircuit {
-    pub fn new(circuit: Circuit) -> Self {
-        let mut keys = HashMap::new();
-
-        // Generate keys for all wires
-        let mut all_wires = std::collections::HashSet::new();
-        for gate in &circuit.gates {
-            all_wires.insert(gate.id);
-            for &input in &gate.inputs {
-                all_wires.insert(input);
-            }
-        }
+/// Garbler's secret state: both labels of every wire and the global offset,
+/// alongside the garbled circuit it produced
+///
+/// Not `Clone`, and only the evaluator view in `garbled_circuit` is meant to
+/// leave the garbler.
+#[derive(Debug)]
+pub struct Garbler {
+    keys: HashMap<u32, (Key, Key)>,
+    delta: Option<Key>,
+    garbled_circuit: GarbledCircuit,
+}
 
-        for wire in all_wires {
-            keys.insert(wire, (Key::new(), Key::new()));
-        }
+impl Garbler {
+    pub fn new(circuit: Circuit) -> Result<Self> {
+        Self::with_scheme(circuit, GarblingScheme::default())
+    }
+
+    /// Garble a circuit, failing if it is not well formed
+    ///
+    /// Gates are garbled, and later evaluated, in topological order.
+    pub fn with_scheme(circuit: Circuit, scheme: GarblingScheme) -> Result<Self> {
+        let gates: Vec<Gate> = circuit.topological_order()?.into_iter().cloned().collect();
+
+        // The permute bit of delta is set so both labels of a wire differ in it
+        let delta = scheme.uses_delta().then(|| Key::new().with_permute_bit(1));
+
+        // Draw a fresh label pair with opposite permute bits, correlated by
+        // delta when the scheme has one
+        let new_pair = || {
+            let key0 = Key::new();
+            let key1 = match &delta {
+                Some(delta) => &key0 ^ delta,
+                None => Key::new().with_permute_bit(1 - key0.permute_bit()),
+            };
+            (key0, key1)
+        };
+
+        // Input wires are the party inputs and the constants
+        let mut keys: HashMap<u32, (Key, Key)> = circuit
+            .input_wires()
+            .chain(circuit.constants.keys().copied())
+            .map(|wire| (wire, new_pair()))
+            .collect();
+        let mut constant_keys: HashMap<u32, Key> = circuit
+            .constants
+            .iter()
+            .map(|(&wire, &value)| (wire, select_key(&keys[&wire], value).clone()))
+            .collect();
 
-        // Create garbled gates
+        // Gates are garbled in topological order so free, half and row-reduced
+        // gates can derive their output labels from the labels of their inputs
         let mut garbled_gates = Vec::new();
-        for gate in &circuit.gates {
-            garbled_gates.push(GarbledGate::new(gate, &keys));
+        for gate in &gates {
+            let pair = match (&delta, scheme) {
+                _ if gate.gate_type.arity() == Some(0) => {
+                    let pair = new_pair();
+                    let value = gate.eval(&[]);
+                    constant_keys.insert(gate.id, select_key(&pair, value).clone());
+                    pair
+                }
+                (Some(delta), _) if scheme.is_free(gate.gate_type) => {
+                    // XOR the 0-labels of the inputs the gate depends on, and
+                    // shift by delta when it negates them
+                    let (coefficients, constant) = gate.gate_type.affine_form().unwrap();
+                    let mut key0 = if constant == 1 {
+                        delta.clone()
+                    } else {
+                        Key::zero()
+                    };
+                    for (wire, &coefficient) in gate.inputs.iter().zip(&coefficients) {
+                        if coefficient == 1 {
+                            key0 = &key0 ^ &keys[wire].0;
+                        }
+                    }
+                    let key1 = &key0 ^ delta;
+                    (key0, key1)
+                }
+                (Some(delta), GarblingScheme::HalfGates | GarblingScheme::ThreeHalves)
+                    if gate.gate_type.and_form().is_some() =>
+                {
+                    let (garbled_gate, key0) = match scheme {
+                        GarblingScheme::HalfGates => GarbledGate::new_half_gate(gate, &keys, delta),
+                        _ => GarbledGate::new_three_halves(gate, &keys, delta),
+                    };
+                    garbled_gates.push(garbled_gate);
+                    let key1 = &key0 ^ delta;
+                    keys.insert(gate.id, (key0, key1));
+                    continue;
+                }
+                (None, GarblingScheme::Grr3) => {
+                    let (garbled_gate, pair) = GarbledGate::new_grr3(gate, &keys);
+                    garbled_gates.push(garbled_gate);
+                    keys.insert(gate.id, pair);
+                    continue;
+                }
+                _ => new_pair(),
+            };
+            keys.insert(gate.id, pair);
+            garbled_gates.push(GarbledGate::new(gate, &keys, scheme));
         }
 
-        GarbledCircuit {
+        let output_decoding = circuit
+            .out
+            .iter()
+            .filter_map(|&wire| {
+                let (key0, key1) = keys.get(&wire)?;
+                Some((wire, (output_hash(key0, wire), output_hash(key1, wire))))
+            })
+            .collect();
+
+        let garbled_circuit = GarbledCircuit {
             circuit,
-            keys,
+            scheme,
             garbled_gates,
-        }
+            constant_keys,
+            output_decoding,
+        };
+        Ok(Garbler {
+            keys,
+            delta,
+            garbled_circuit,
+        })
+    }
+
+    /// The garbled circuit to send to the evaluator
+    pub fn garbled_circuit(&self) -> &GarbledCircuit {
+        &self.garbled_circuit
+    }
+
+    /// Global offset between the two labels of every wire, if the scheme uses one
+    pub fn delta(&self) -> Option<&Key> {
+        self.delta.as_ref()
     }
 
+    /// Both labels of a party input wire, `None` for any other wire
+    pub fn input_keys(&self, wire: u32) -> Option<&(Key, Key)> {
+        let circuit = &self.garbled_circuit.circuit;
+        let is_input = circuit
+            .alice
+            .iter()
+            .chain(circuit.bob.iter())
+            .any(|wires| wires.contains(&wire));
+        is_input.then(|| &self.keys[&wire])
+    }
+
+    /// Label encoding `bit` on a party input wire
+    pub fn encode_input(&self, wire: u32, bit: u8) -> Option<&Key> {
+        self.input_keys(wire).map(|pair| select_key(pair, bit))
+    }
+}
+
+impl GarbledCircuit {
     /// Evaluate all gates given initial wire values
-    pub fn evaluate_gates(&self, mut wire_values: HashMap<u32, Key>) -> HashMap<u32, Key> {
+    ///
+    /// The labels of constants are filled in from `constant_keys`, so only the
+    /// party inputs need to be given; a missing one is reported as
+    /// `Error::MissingInput` and a gate with the wrong number of inputs as
+    /// `Error::MalformedCircuit`.
+    pub fn evaluate_gates(&self, mut wire_values: HashMap<u32, Key>) -> Result<HashMap<u32, Key>> {
+        wire_values.extend(self.constant_keys.clone());
+
         // Evaluate gates in order
         for gate in &self.garbled_gates {
-            match gate.gate_type.as_str() {
-                "NOT" => {
-                    let input_key = wire_values[&gate.inputs[0]].clone();
-
-                    // Try both possible input values to find the correct one
-                    for bit in 0..2 {
-                        let index = vec![bit];
-                        if let Some(encrypted) = gate.garbled_table.get(&index) {
-                            if let Ok(decrypted) =
-                                input_key.decrypt_with_magic_verification(encrypted)
-                            {
-                                if decrypted.len() >= AES_KEY_SIZE {
-                                    let mut key_bytes = [0u8; AES_KEY_SIZE];
-                                    key_bytes.copy_from_slice(&decrypted[0..AES_KEY_SIZE]);
-                                    wire_values.insert(gate.id, Key(key_bytes));
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-                "AND" | "OR" | "XOR" => {
-                    let key_a = wire_values[&gate.inputs[0]].clone();
-                    let key_b = wire_values[&gate.inputs[1]].clone();
-
-                    // Try all possible input combinations
-                    let mut found = false;
-                    for a_bit in 0..2 {
-                        for b_bit in 0..2 {
-                            let index = vec![a_bit, b_bit];
-                            if let Some(encrypted_twice) = gate.garbled_table.get(&index) {
-                                if let Ok(encrypted_once) =
-                                    key_b.decrypt_with_magic_verification(encrypted_twice)
-                                {
-                                    if let Ok(decrypted) =
-                                        key_a.decrypt_with_magic_verification(&encrypted_once)
-                                    {
-                                        if decrypted.len() >= AES_KEY_SIZE {
-                                            let mut key_bytes = [0u8; AES_KEY_SIZE];
-                                            key_bytes.copy_from_slice(&decrypted[0..AES_KEY_SIZE]);
-                                            wire_values.insert(gate.id, Key(key_bytes));
-                                            found = true;
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        if found {
-                            break;
-                        }
-                    }
+            match gate.gate_type.arity() {
+                Some(0) => continue,
+                // Gates come from the garbler, so their shape is not trusted
+                Some(expected) if expected != gate.inputs.len() => {
+                    return Err(Error::MalformedCircuit(CircuitError::WrongArity {
+                        gate: gate.id,
+                        expected,
+                        found: gate.inputs.len(),
+                    }));
                 }
-                _ => panic!("Unsupported gate type: {}", gate.gate_type),
+                _ => {}
+            }
+            let input_keys = gate
+                .inputs
+                .iter()
+                .map(|wire| wire_values.get(wire).ok_or(Error::MissingInput(*wire)))
+                .collect::<Result<Vec<&Key>>>()?;
+
+            if let (true, Some((coefficients, _))) = (
+                self.scheme.is_free(gate.gate_type),
+                gate.gate_type.affine_form(),
+            ) {
+                // Negations are already folded into the labels by the garbler
+                let key = input_keys
+                    .iter()
+                    .zip(&coefficients)
+                    .filter(|(_, &coefficient)| coefficient == 1)
+                    .fold(Key::zero(), |key, (input_key, _)| &key ^ input_key);
+                wire_values.insert(gate.id, key);
+                continue;
             }
+
+            // The permute bits point at the single row these labels open
+            let and_like = gate.gate_type.and_form().is_some();
+            let key = match self.scheme {
+                GarblingScheme::Grr3 => gate.evaluate_grr3(&input_keys),
+                GarblingScheme::HalfGates if and_like => {
+                    gate.evaluate_half_gate(input_keys[0], input_keys[1])
+                }
+                GarblingScheme::ThreeHalves if and_like => {
+                    gate.evaluate_three_halves(input_keys[0], input_keys[1])
+                }
+                _ => gate.evaluate_table(&input_keys),
+            }?;
+            wire_values.insert(gate.id, key);
         }
 
-        wire_values
+        Ok(wire_values)
+    }
+
+    /// Decode the label of an output wire using only the published hashes
+    pub fn decode_output(&self, wire: u32, key: &Key) -> Result<u8> {
+        let (hash0, hash1) = self
+            .output_decoding
+            .get(&wire)
+            .ok_or(Error::InvalidOutputLabel(wire))?;
+        let hash = output_hash(key, wire);
+        if &hash == hash0 {
+            Ok(0)
+        } else if &hash == hash1 {
+            Ok(1)
+        } else {
+            Err(Error::InvalidOutputLabel(wire))
+        }
     }
 
     /// Determine output values from final wire keys
-    pub fn extract_outputs(&self, wire_values: &HashMap<u32, Key>) -> HashMap<u32, u8> {
+    pub fn extract_outputs(&self, wire_values: &HashMap<u32, Key>) -> Result<HashMap<u32, u8>> {
         let mut results = HashMap::new();
         for &output_wire in &self.circuit.out {
-            if let Some(result_key) = wire_values.get(&output_wire) {
-                let output_keys = &self.keys[&output_wire];
-                if result_key == &output_keys.0 {
-                    results.insert(output_wire, 0);
-                } else if result_key == &output_keys.1 {
-                    results.insert(output_wire, 1);
-                } else {
-                    panic!("Unable to determine output for wire {output_wire}");
-                }
-            }
+            let result_key = wire_values
+                .get(&output_wire)
+                .ok_or(Error::InvalidOutputLabel(output_wire))?;
+            results.insert(output_wire, self.decode_output(output_wire, result_key)?);
         }
-        results
-    }
-
-    /// Get keys for debugging purposes (not secure in real protocol)
-    pub fn get_all_keys(&self) -> &HashMap<u32, (Key, Key)> {
-        &self.keys
+        Ok(results)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::circuit::{Circuit, Gate};
+    use crate::circuit::{Circuit, CircuitError, Gate, GateType};
+    use std::collections::HashSet;
 
     fn create_test_and_circuit() -> Circuit {
         Circuit {
             id: "test_and".to_string(),
-            gates: vec![Gate {
-                id: 3,
-                gate_type: "AND".to_string(),
-                inputs: vec![1, 2],
-            }],
+            gates: vec![Gate::new(3, GateType::And, vec![1, 2])],
             alice: Some(vec![1]),
             bob: Some(vec![2]),
             out: vec![3],
+            constants: HashMap::new(),
         }
     }
 
     #[test]
     fn test_garbled_circuit_creation() {
         let circuit = create_test_and_circuit();
-        let garbled_circuit = GarbledCircuit::new(circuit.clone());
+        let garbler = Garbler::new(circuit.clone()).unwrap();
+        let garbled_circuit = garbler.garbled_circuit();
 
         assert_eq!(garbled_circuit.circuit.id, "test_and");
         assert_eq!(garbled_circuit.garbled_gates.len(), 1);
-        assert_eq!(garbled_circuit.garbled_gates[0].gate_type, "AND");
+        assert_eq!(garbled_circuit.garbled_gates[0].gate_type, GateType::And);
 
         // Should have keys for all wires (1, 2, 3)
-        assert!(garbled_circuit.keys.contains_key(&1));
-        assert!(garbled_circuit.keys.contains_key(&2));
-        assert!(garbled_circuit.keys.contains_key(&3));
+        assert!(garbler.keys.contains_key(&1));
+        assert!(garbler.keys.contains_key(&2));
+        assert!(garbler.keys.contains_key(&3));
     }
 
     #[test]
     fn test_garbled_gate_table_creation() {
         let circuit = create_test_and_circuit();
-        let garbled_circuit = GarbledCircuit::new(circuit);
+        let garbler = Garbler::new(circuit).unwrap();
+        let garbled_circuit = garbler.garbled_circuit();
 
         let garbled_gate = &garbled_circuit.garbled_gates[0];
 
         // AND gate should have 4 entries in garbled table (2^2 combinations)
         assert_eq!(garbled_gate.garbled_table.len(), 4);
 
-        // Check that all possible input combinations exist
+        // Every row is filled, one per permute bit combination
+        assert!(garbled_gate.garbled_table.iter().all(|row| !row.is_empty()));
+    }
+
+    fn evaluate_locally(garbler: &Garbler, inputs: &[(u32, u8)]) -> HashMap<u32, u8> {
+        let wire_values = inputs
+            .iter()
+            .map(|&(wire, bit)| {
+                let (key0, key1) = &garbler.keys[&wire];
+                (wire, if bit == 0 { key0 } else { key1 }.clone())
+            })
+            .collect();
+        let garbled_circuit = garbler.garbled_circuit();
+        let final_wire_values = garbled_circuit.evaluate_gates(wire_values).unwrap();
+        garbled_circuit.extract_outputs(&final_wire_values).unwrap()
+    }
+
+    #[test]
+    fn test_free_xor_labels_share_delta() {
+        let circuit = Circuit {
+            id: "xor_not".to_string(),
+            gates: vec![
+                Gate::new(3, GateType::Xor, vec![1, 2]),
+                Gate::new(4, GateType::Not, vec![3]),
+                Gate::new(5, GateType::And, vec![4, 1]),
+            ],
+            alice: Some(vec![1]),
+            bob: Some(vec![2]),
+            out: vec![5],
+            constants: HashMap::new(),
+        };
+        let garbler = Garbler::with_scheme(circuit, GarblingScheme::FreeXor).unwrap();
+        let garbled_circuit = garbler.garbled_circuit();
+        let delta = garbler.delta.clone().unwrap();
+
+        // Every wire's 1-label is its 0-label shifted by the global delta
+        for (key0, key1) in garbler.keys.values() {
+            assert_eq!(&(key0 ^ key1), &delta);
+        }
+
+        // XOR and NOT gates carry no table, AND still does
+        assert!(garbled_circuit.garbled_gates[0].garbled_table.is_empty());
+        assert!(garbled_circuit.garbled_gates[1].garbled_table.is_empty());
+        assert_eq!(garbled_circuit.garbled_gates[2].garbled_table.len(), 4);
+    }
+
+    #[test]
+    fn test_free_xor_evaluation() {
+        let circuit = Circuit {
+            id: "xnor".to_string(),
+            gates: vec![
+                Gate::new(3, GateType::Xor, vec![1, 2]),
+                Gate::new(4, GateType::Not, vec![3]),
+            ],
+            alice: Some(vec![1]),
+            bob: Some(vec![2]),
+            out: vec![3, 4],
+            constants: HashMap::new(),
+        };
+
+        for scheme in GarblingScheme::ALL {
+            let garbler = Garbler::with_scheme(circuit.clone(), scheme).unwrap();
+            for a in 0..2 {
+                for b in 0..2 {
+                    let outputs = evaluate_locally(&garbler, &[(1, a), (2, b)]);
+                    assert_eq!(outputs[&3], a ^ b, "{scheme:?} XOR({a}, {b})");
+                    assert_eq!(outputs[&4], 1 - (a ^ b), "{scheme:?} XNOR({a}, {b})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_all_gate_types_evaluate() {
+        let gate_types = [
+            GateType::Zero,
+            GateType::One,
+            GateType::And,
+            GateType::Nand,
+            GateType::Or,
+            GateType::Nor,
+            GateType::Xor,
+            GateType::Xnor,
+            GateType::NImply,
+            GateType::CNImply,
+            GateType::Imply,
+            GateType::CImply,
+            GateType::ProjA,
+            GateType::ProjB,
+            GateType::NotA,
+            GateType::NotB,
+            GateType::Not,
+            GateType::Buf,
+        ];
+        let circuit = Circuit {
+            id: "all_types".to_string(),
+            gates: gate_types
+                .iter()
+                .enumerate()
+                .map(|(i, &gate_type)| {
+                    let arity = gate_type.arity().unwrap();
+                    Gate::new(3 + i as u32, gate_type, vec![1, 2][..arity].to_vec())
+                })
+                .collect(),
+            alice: Some(vec![1]),
+            bob: Some(vec![2]),
+            out: (3..3 + gate_types.len() as u32).collect(),
+            constants: HashMap::new(),
+        };
+
+        for scheme in GarblingScheme::ALL {
+            let garbler = Garbler::with_scheme(circuit.clone(), scheme).unwrap();
+            for a in 0..2 {
+                for b in 0..2 {
+                    let outputs = evaluate_locally(&garbler, &[(1, a), (2, b)]);
+                    for (i, gate_type) in gate_types.iter().enumerate() {
+                        let arity = gate_type.arity().unwrap();
+                        let expected = gate_type.eval(&[a, b][..arity]).unwrap();
+                        assert_eq!(
+                            outputs[&(3 + i as u32)],
+                            expected,
+                            "{scheme:?} {gate_type:?}({a}, {b})"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lut_gate_single_table() {
+        // 3-bit S-box output bit and a 2-input LUT feeding a regular gate
+        let sbox_bit = vec![0, 1, 1, 0, 1, 0, 0, 1];
+        let circuit = Circuit {
+            id: "lut".to_string(),
+            gates: vec![
+                Gate::lut(4, vec![1, 2, 3], sbox_bit.clone()),
+                Gate::lut(5, vec![4, 1], vec![1, 0, 0, 1]),
+                Gate::new(6, GateType::And, vec![5, 2]),
+            ],
+            alice: Some(vec![1, 2]),
+            bob: Some(vec![3]),
+            out: vec![4, 6],
+            constants: HashMap::new(),
+        };
+
+        for scheme in GarblingScheme::ALL {
+            let garbler = Garbler::with_scheme(circuit.clone(), scheme).unwrap();
+            let garbled_circuit = garbler.garbled_circuit();
+            let rows = garbled_circuit.garbled_gates[0].garbled_table.len();
+            let expected_rows = if scheme == GarblingScheme::Grr3 { 7 } else { 8 };
+            assert_eq!(rows, expected_rows, "{scheme:?}");
+
+            for row in 0..8u8 {
+                let (a, b, c) = (row >> 2, (row >> 1) & 1, row & 1);
+                let outputs = evaluate_locally(&garbler, &[(1, a), (2, b), (3, c)]);
+                let lut = sbox_bit[row as usize];
+                assert_eq!(outputs[&4], lut, "{scheme:?} row {row}");
+                assert_eq!(outputs[&6], (1 ^ lut ^ a) & b, "{scheme:?} row {row}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_constants_are_free() {
+        // a ^ 1 from a constant wire, and (b & ONE) | ZERO from constant gates
+        let mut circuit = Circuit {
+            id: "constants".to_string(),
+            gates: vec![
+                Gate::new(4, GateType::Xor, vec![1, 3]),
+                Gate::new(5, GateType::One, vec![]),
+                Gate::new(6, GateType::Zero, vec![]),
+                Gate::new(7, GateType::And, vec![2, 5]),
+                Gate::new(8, GateType::Or, vec![7, 6]),
+            ],
+            alice: Some(vec![1]),
+            bob: Some(vec![2]),
+            out: vec![3, 4, 8],
+            constants: HashMap::new(),
+        };
+        circuit.constants.insert(3, 1);
+
+        for scheme in GarblingScheme::ALL {
+            let garbler = Garbler::with_scheme(circuit.clone(), scheme).unwrap();
+            let garbled_circuit = garbler.garbled_circuit();
+            assert!(garbled_circuit.garbled_gates[1].garbled_table.is_empty());
+            assert!(garbled_circuit.garbled_gates[2].garbled_table.is_empty());
+            assert_eq!(garbled_circuit.constant_keys.len(), 3);
+
+            for a in 0..2 {
+                for b in 0..2 {
+                    let outputs = evaluate_locally(&garbler, &[(1, a), (2, b)]);
+                    assert_eq!(outputs[&3], 1, "{scheme:?}");
+                    assert_eq!(outputs[&4], a ^ 1, "{scheme:?} {a} ^ 1");
+                    assert_eq!(outputs[&8], b, "{scheme:?} ({b} & 1) | 0");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gates_garbled_in_topological_order() {
+        let mut circuit = create_test_and_circuit();
+        circuit
+            .gates
+            .insert(0, Gate::new(4, GateType::Not, vec![3]));
+        circuit.out = vec![4];
+
+        let garbler = Garbler::new(circuit.clone()).unwrap();
+        let order: Vec<u32> = garbler
+            .garbled_circuit()
+            .garbled_gates
+            .iter()
+            .map(|gate| gate.id)
+            .collect();
+        assert_eq!(order, vec![3, 4]);
+        for a in 0..2 {
+            for b in 0..2 {
+                let outputs = evaluate_locally(&garbler, &[(1, a), (2, b)]);
+                assert_eq!(outputs[&4], 1 - (a & b), "!({a} & {b})");
+            }
+        }
+
+        // A cycle is rejected before anything is garbled
+        circuit.gates[1].inputs = vec![1, 4];
+        assert!(matches!(
+            Garbler::new(circuit),
+            Err(Error::MalformedCircuit(CircuitError::Cycle(4)))
+        ));
+    }
+
+    #[test]
+    fn test_output_decoding() {
+        let circuit = create_test_and_circuit();
+        let garbler = Garbler::new(circuit).unwrap();
+        let garbled_circuit = garbler.garbled_circuit();
+        let (key0, key1) = &garbler.keys[&3];
+
+        // Only party inputs are handed out by the garbler
+        assert!(garbler.input_keys(1).is_some());
+        assert!(garbler.input_keys(3).is_none());
+
+        // The published hashes reveal neither label
+        let (hash0, hash1) = &garbled_circuit.output_decoding[&3];
+        assert_ne!(hash0, key0);
+        assert_ne!(hash1, key1);
+
+        assert_eq!(garbled_circuit.decode_output(3, key0).unwrap(), 0);
+        assert_eq!(garbled_circuit.decode_output(3, key1).unwrap(), 1);
+        assert!(matches!(
+            garbled_circuit.decode_output(3, &Key::new()),
+            Err(Error::InvalidOutputLabel(3))
+        ));
+        assert!(matches!(
+            garbled_circuit.decode_output(1, key0),
+            Err(Error::InvalidOutputLabel(1))
+        ));
+
+        let wire_values = HashMap::from([(3, Key::new())]);
+        assert!(garbled_circuit.extract_outputs(&wire_values).is_err());
+    }
+
+    #[test]
+    fn test_evaluation_errors() {
+        let circuit = create_test_and_circuit();
+
+        for scheme in GarblingScheme::ALL {
+            let garbler = Garbler::with_scheme(circuit.clone(), scheme).unwrap();
+            let mut garbled_circuit = garbler.garbled_circuit().clone();
+            let wire_values = HashMap::from([(1, garbler.keys[&1].0.clone())]);
+            assert!(matches!(
+                garbled_circuit.evaluate_gates(wire_values),
+                Err(Error::MissingInput(2))
+            ));
+
+            // A truncated table is reported instead of panicking. Labels with
+            // permute bit 1 avoid the implicit first row of GRR3.
+            let odd_label = |wire: u32| {
+                let (key0, key1) = &garbler.keys[&wire];
+                if key0.permute_bit() == 1 { key0 } else { key1 }.clone()
+            };
+            let wire_values = HashMap::from([(1, odd_label(1)), (2, odd_label(2))]);
+            garbled_circuit.garbled_gates[0].garbled_table.clear();
+            let result = garbled_circuit.evaluate_gates(wire_values.clone());
+            assert!(matches!(result, Err(Error::Decryption(_))), "{scheme:?}");
+
+            // So is a gate with too few inputs
+            garbled_circuit.garbled_gates[0].inputs.pop();
+            let result = garbled_circuit.evaluate_gates(wire_values);
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::MalformedCircuit(CircuitError::WrongArity {
+                        gate: 3,
+                        ..
+                    }))
+                ),
+                "{scheme:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_permute_bits_select_single_row() {
+        let circuit = create_test_and_circuit();
+
+        for scheme in [GarblingScheme::Classic, GarblingScheme::FreeXor] {
+            let garbler = Garbler::with_scheme(circuit.clone(), scheme).unwrap();
+            let garbled_circuit = garbler.garbled_circuit();
+            let gate = &garbled_circuit.garbled_gates[0];
+
+            // The two labels of every wire carry opposite permute bits
+            for (key0, key1) in garbler.keys.values() {
+                assert_ne!(key0.permute_bit(), key1.permute_bit());
+            }
+
+            // Each label pair opens exactly the row its permute bits point at
+            let output_keys = &garbler.keys[&3];
+            for key_a in [&garbler.keys[&1].0, &garbler.keys[&1].1] {
+                for key_b in [&garbler.keys[&2].0, &garbler.keys[&2].1] {
+                    let selected = row_index(&[key_a, key_b]);
+                    let mask = row_mask(&[key_a, key_b], gate.id);
+                    for (index, row) in gate.garbled_table.iter().enumerate() {
+                        let opened = &mask ^ &Key(row[..].try_into().unwrap());
+                        let valid = opened == output_keys.0 || opened == output_keys.1;
+                        assert_eq!(valid, index == selected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gate_tweaks_are_disjoint() {
+        // Each gate uses its table tweak plus those of its AND-like scheme
+        let ids = [0, 1, 2, 3, 6, u32::MAX];
+        for three_halves in [false, true] {
+            let mut seen = HashSet::new();
+            for &id in &ids {
+                let mut tweaks = vec![table_tweak(id)];
+                if three_halves {
+                    tweaks.extend(three_halves_tweaks(id));
+                } else {
+                    let (tweak_g, tweak_e) = half_gate_tweaks(id);
+                    tweaks.extend([tweak_g, tweak_e]);
+                }
+                for tweak in tweaks {
+                    assert!(tweak & OUTPUT_TWEAK == 0);
+                    assert!(seen.insert(tweak), "tweak {tweak} of gate {id} reused");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_grr3_drops_first_row() {
+        let mut circuit = create_test_and_circuit();
+        circuit.gates.push(Gate::new(4, GateType::Not, vec![3]));
+        circuit.gates.push(Gate::new(5, GateType::Xor, vec![4, 1]));
+        circuit.out = vec![4, 5];
+        let garbler = Garbler::with_scheme(circuit, GarblingScheme::Grr3).unwrap();
+        let garbled_circuit = garbler.garbled_circuit();
+        assert!(garbler.delta.is_none());
+
+        // Binary gates send three rows and NOT sends one
+        let rows: Vec<usize> = garbled_circuit
+            .garbled_gates
+            .iter()
+            .map(|gate| gate.garbled_table.len())
+            .collect();
+        assert_eq!(rows, vec![3, 1, 3]);
+
+        for a in 0..2 {
+            for b in 0..2 {
+                let outputs = evaluate_locally(&garbler, &[(1, a), (2, b)]);
+                assert_eq!(outputs[&4], 1 - (a & b), "!({a} & {b})");
+                assert_eq!(outputs[&5], (1 - (a & b)) ^ a, "!({a} & {b}) ^ {a}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_half_gates_use_two_ciphertexts() {
+        let mut circuit = create_test_and_circuit();
+        circuit.gates.push(Gate::new(4, GateType::Or, vec![3, 2]));
+        circuit.out = vec![4];
+        let garbler = Garbler::with_scheme(circuit, GarblingScheme::HalfGates).unwrap();
+        let garbled_circuit = garbler.garbled_circuit();
+
+        for gate in &garbled_circuit.garbled_gates {
+            assert_eq!(gate.garbled_table.len(), 2);
+            assert!(gate.garbled_table.iter().all(|row| row.len() == 16));
+        }
+
+        // (a & b) | b == b
         for a in 0..2 {
             for b in 0..2 {
-                let index = vec![a, b];
-                assert!(garbled_gate.garbled_table.contains_key(&index));
+                let outputs = evaluate_locally(&garbler, &[(1, a), (2, b)]);
+                assert_eq!(outputs[&4], b, "(({a} & {b}) | {b})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_three_halves_table_size() {
+        let mut circuit = create_test_and_circuit();
+        circuit.gates.push(Gate::new(4, GateType::Or, vec![3, 1]));
+        circuit.out = vec![3, 4];
+
+        // Control bits are random per garbling, so garble repeatedly
+        for _ in 0..20 {
+            let garbler =
+                Garbler::with_scheme(circuit.clone(), GarblingScheme::ThreeHalves).unwrap();
+            let garbled_circuit = garbler.garbled_circuit();
+            for gate in &garbled_circuit.garbled_gates {
+                let sizes: Vec<usize> = gate.garbled_table.iter().map(|row| row.len()).collect();
+                assert_eq!(sizes, vec![8, 8, 8, 2]);
+            }
+
+            for a in 0..2 {
+                for b in 0..2 {
+                    let outputs = evaluate_locally(&garbler, &[(1, a), (2, b)]);
+                    assert_eq!(outputs[&3], a & b, "{a} & {b}");
+                    assert_eq!(outputs[&4], (a & b) | a, "({a} & {b}) | {a}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_schemes_evaluate_max_circuit() {
+        let circuit = Circuit::from_json_file("circuits/max.json")
+            .unwrap()
+            .remove(0);
+
+        for scheme in GarblingScheme::ALL {
+            let garbler = Garbler::with_scheme(circuit.clone(), scheme).unwrap();
+            for a in 0..4u8 {
+                for b in 0..4u8 {
+                    let inputs = [(1, a >> 1), (2, a & 1), (3, b >> 1), (4, b & 1)];
+                    let outputs = evaluate_locally(&garbler, &inputs);
+                    let max = (outputs[&10] << 1) | outputs[&19];
+                    assert_eq!(max, a.max(b), "{scheme:?} max({a}, {b})");
+                }
             }
         }
     }
@@ -294,10 +1183,10 @@ mod tests {
     #[test]
     fn test_wire_key_consistency() {
         let circuit = create_test_and_circuit();
-        let garbled_circuit = GarbledCircuit::new(circuit);
+        let garbler = Garbler::new(circuit).unwrap();
 
         // Get all keys
-        let keys = garbled_circuit.get_all_keys();
+        let keys = &garbler.keys;
 
         // Each wire should have exactly 2 keys (for 0 and 1)
         for (wire_id, (key0, key1)) in keys {
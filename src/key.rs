@@ -1,9 +1,17 @@
-use aes_gcm::aead::Aead;
-use aes_gcm::{Aes128Gcm, Key as AesKey, KeyInit, Nonce};
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
 use rand::Rng;
+use std::ops::BitXor;
+use std::sync::OnceLock;
 
-// Magic bytes for key verification
-const MAGIC_BYTES: &[u8] = b"GARB";
+// Public key of the fixed-key AES permutation behind `Key::hash`
+const FIXED_AES_KEY: [u8; 16] = *b"garbled-circuit!";
+
+/// Fixed-key AES permutation, its key schedule expanded once per process
+fn fixed_key_cipher() -> &'static Aes128 {
+    static CIPHER: OnceLock<Aes128> = OnceLock::new();
+    CIPHER.get_or_init(|| Aes128::new(&FIXED_AES_KEY.into()))
+}
 
 // Simplified key type - just a 16-byte array for AES
 #[derive(Clone, Debug, PartialEq)]
@@ -17,49 +25,86 @@ impl Key {
         Key(key)
     }
 
-    pub fn encrypt(&self, data: &[u8]) -> Vec<u8> {
-        let cipher = Aes128Gcm::new(AesKey::<Aes128Gcm>::from_slice(&self.0));
-        let nonce = Nonce::from_slice(&[0u8; 12]); // Fixed nonce for simplicity
-        cipher.encrypt(nonce, data).unwrap()
+    /// All-zero key, the identity for XOR
+    pub fn zero() -> Self {
+        Key([0u8; 16])
     }
 
-    pub fn encrypt_with_magic(&self, data: &[u8]) -> Vec<u8> {
-        // Prepend magic bytes to the data before encryption
-        let mut data_with_magic = Vec::new();
-        data_with_magic.extend_from_slice(MAGIC_BYTES);
-        data_with_magic.extend_from_slice(data);
+    /// Point-and-permute bit, stored in the least significant bit of the key
+    pub fn permute_bit(&self) -> u8 {
+        self.0[0] & 1
+    }
+
+    /// Tweakable correlation-robust hash `H(x, i) = pi(y) ^ y` with
+    /// `y = sigma(x) ^ i`
+    ///
+    /// `pi` is fixed-key AES and `sigma(l || r) = (l ^ r) || l` is the linear
+    /// orthomorphism of Guo et al., which keeps the hash robust to the
+    /// correlated labels of Free XOR. Distinct tweaks give independent-looking
+    /// outputs for the same key.
+    pub fn hash(&self, tweak: u64) -> Key {
+        let mut input = [0u8; 16];
+        for i in 0..8 {
+            input[i] = self.0[i] ^ self.0[i + 8];
+            input[i + 8] = self.0[i];
+        }
+        for (byte, tweak_byte) in input.iter_mut().zip(tweak.to_le_bytes()) {
+            *byte ^= tweak_byte;
+        }
 
-        let cipher = Aes128Gcm::new(AesKey::<Aes128Gcm>::from_slice(&self.0));
-        let nonce = Nonce::from_slice(&[0u8; 12]); // Fixed nonce for simplicity
-        cipher.encrypt(nonce, data_with_magic.as_slice()).unwrap()
+        let mut block = input.into();
+        fixed_key_cipher().encrypt_block(&mut block);
+        let mut out = [0u8; 16];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = block[i] ^ input[i];
+        }
+        Key(out)
     }
 
-    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, &'static str> {
-        let cipher = Aes128Gcm::new(AesKey::<Aes128Gcm>::from_slice(&self.0));
-        let nonce = Nonce::from_slice(&[0u8; 12]); // Fixed nonce for simplicity
-        cipher.decrypt(nonce, data).map_err(|_| "Decryption failed")
+    /// Hash of several keys under one tweak, `H(2A ^ 4B ^ ..., tweak)` with the
+    /// last key doubled once and each earlier key doubled once more
+    ///
+    /// Doubling keeps the keys apart, so the hash of a row depends on every
+    /// label in it and rows sharing a label stay unrelated.
+    pub fn hash_many(keys: &[&Key], tweak: u64) -> Key {
+        keys.iter()
+            .fold(Key::zero(), |acc, key| (&acc ^ key).double())
+            .hash(tweak)
     }
 
-    pub fn decrypt_with_magic_verification(&self, data: &[u8]) -> Result<Vec<u8>, &'static str> {
-        let cipher = Aes128Gcm::new(AesKey::<Aes128Gcm>::from_slice(&self.0));
-        let nonce = Nonce::from_slice(&[0u8; 12]); // Fixed nonce for simplicity
+    /// Multiplication by `x` in GF(2^128) modulo `x^128 + x^7 + x^2 + x + 1`,
+    /// reading the key as a little-endian integer
+    pub fn double(&self) -> Key {
+        let value = u128::from_le_bytes(self.0);
+        let carry = (value >> 127) as u8;
+        let doubled = (value << 1) ^ (0x87 * carry as u128);
+        Key(doubled.to_le_bytes())
+    }
 
-        match cipher.decrypt(nonce, data) {
-            Ok(decrypted) => {
-                // Verify magic bytes
-                if decrypted.len() < MAGIC_BYTES.len() {
-                    return Err("Decrypted data too short");
-                }
+    /// Copy of this key with its point-and-permute bit forced to `bit`
+    pub fn with_permute_bit(mut self, bit: u8) -> Self {
+        self.0[0] = (self.0[0] & !1) | (bit & 1);
+        self
+    }
+}
 
-                if &decrypted[0..MAGIC_BYTES.len()] != MAGIC_BYTES {
-                    return Err("Invalid magic bytes");
-                }
+impl BitXor for &Key {
+    type Output = Key;
 
-                // Return data without magic bytes
-                Ok(decrypted[MAGIC_BYTES.len()..].to_vec())
-            }
-            Err(_) => Err("Decryption failed"),
+    fn bitxor(self, other: &Key) -> Key {
+        let mut out = [0u8; 16];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = self.0[i] ^ other.0[i];
         }
+        Key(out)
+    }
+}
+
+impl BitXor for Key {
+    type Output = Key;
+
+    fn bitxor(self, other: Key) -> Key {
+        &self ^ &other
     }
 }
 
@@ -97,90 +142,73 @@ mod tests {
     }
 
     #[test]
-    fn test_key_encrypt_decrypt() {
-        let key = Key::new();
-        let data = b"Hello, World!";
-
-        let encrypted = key.encrypt(data);
-        let decrypted = key.decrypt(&encrypted).unwrap();
-
-        assert_eq!(data.to_vec(), decrypted);
-    }
-
-    #[test]
-    fn test_key_encrypt_decrypt_with_magic() {
-        let key = Key::new();
-        let data = b"Secret message";
-
-        let encrypted = key.encrypt_with_magic(data);
-        let decrypted = key.decrypt_with_magic_verification(&encrypted).unwrap();
+    fn test_key_xor() {
+        let key1 = Key::new();
+        let key2 = Key::new();
 
-        assert_eq!(data.to_vec(), decrypted);
+        // XOR is its own inverse and zero is the identity
+        assert_eq!(&(&key1 ^ &key2) ^ &key2, key1);
+        assert_eq!(&key1 ^ &Key::zero(), key1);
+        assert_eq!(&key1 ^ &key1, Key::zero());
     }
 
     #[test]
-    fn test_magic_bytes_verification() {
+    fn test_key_hash() {
         let key = Key::new();
-        let data = b"Test data";
 
-        let encrypted_with_magic = key.encrypt_with_magic(data);
-        let encrypted_without_magic = key.encrypt(data);
-
-        // Should succeed with magic bytes
-        assert!(key
-            .decrypt_with_magic_verification(&encrypted_with_magic)
-            .is_ok());
-
-        // Should fail without magic bytes
-        assert!(key
-            .decrypt_with_magic_verification(&encrypted_without_magic)
-            .is_err());
+        // Deterministic, and separated by tweak
+        assert_eq!(key.hash(7), key.hash(7));
+        assert_ne!(key.hash(7), key.hash(8));
+        assert_ne!(key.hash(7), Key::new().hash(7));
     }
 
     #[test]
-    fn test_decrypt_with_wrong_key() {
-        let key1 = Key::new();
-        let key2 = Key::new();
-        let data = b"Secret data";
-
-        let encrypted = key1.encrypt(data);
-
-        // Should fail with wrong key
-        assert!(key2.decrypt(&encrypted).is_err());
+    fn test_hash_many() {
+        let key_a = Key::new();
+        let key_b = Key::new();
+
+        // Order and every key matter, a single key is its doubled hash
+        assert_ne!(
+            Key::hash_many(&[&key_a, &key_b], 3),
+            Key::hash_many(&[&key_b, &key_a], 3)
+        );
+        assert_ne!(
+            Key::hash_many(&[&key_a, &key_b], 3),
+            Key::hash_many(&[&key_a, &Key::new()], 3)
+        );
+        assert_eq!(Key::hash_many(&[&key_a], 3), key_a.double().hash(3));
     }
 
     #[test]
-    fn test_magic_verification_with_wrong_key() {
-        let key1 = Key::new();
-        let key2 = Key::new();
-        let data = b"Secret data";
-
-        let encrypted = key1.encrypt_with_magic(data);
-
-        // Should fail with wrong key
-        assert!(key2.decrypt_with_magic_verification(&encrypted).is_err());
-    }
-
-    #[test]
-    fn test_empty_data_encryption() {
-        let key = Key::new();
-        let empty_data = b"";
+    fn test_double() {
+        let mut one = [0u8; 16];
+        one[0] = 1;
+        let mut top = [0u8; 16];
+        top[15] = 0x80;
+        let mut reduced = [0u8; 16];
+        reduced[0] = 0x87;
 
-        let encrypted = key.encrypt(empty_data);
-        let decrypted = key.decrypt(&encrypted).unwrap();
+        assert_eq!(Key(one).double().0[0], 2);
+        assert_eq!(Key(top).double(), Key(reduced));
 
-        assert_eq!(empty_data.to_vec(), decrypted);
+        // Doubling is linear
+        let (key1, key2) = (Key::new(), Key::new());
+        assert_eq!((&key1 ^ &key2).double(), &key1.double() ^ &key2.double());
     }
 
     #[test]
-    fn test_large_data_encryption() {
+    fn test_permute_bit() {
         let key = Key::new();
-        let large_data = vec![42u8; 1000];
 
-        let encrypted = key.encrypt(&large_data);
-        let decrypted = key.decrypt(&encrypted).unwrap();
+        assert_eq!(key.clone().with_permute_bit(0).permute_bit(), 0);
+        assert_eq!(key.clone().with_permute_bit(1).permute_bit(), 1);
 
-        assert_eq!(large_data, decrypted);
+        // Only the permute bit changes
+        let flipped = key.clone().with_permute_bit(1 - key.permute_bit());
+        assert_eq!(
+            &(&key ^ &flipped).0[..],
+            &[1u8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
     }
 
     #[test]
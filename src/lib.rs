@@ -1,9 +1,28 @@
+mod aiger;
+mod blif;
+mod bristol;
+pub mod builder;
 pub mod circuit;
+pub mod equiv;
+pub mod error;
+mod expression;
+pub mod gadgets;
 pub mod garbled;
 pub mod key;
+pub mod optimize;
+pub mod simulate;
+pub mod stats;
+#[cfg(test)]
+mod test_support;
 pub mod two_party;
+pub mod yosys;
 
-pub use circuit::{Circuit, Gate};
-pub use garbled::{GarbledCircuit, GarbledGate};
+pub use builder::{Bit, CircuitBuilder, UInt};
+pub use circuit::{Circuit, CircuitError, Gate, GateType};
+pub use equiv::{Counterexample, Equivalence};
+pub use error::{Error, Result};
+pub use garbled::{GarbledCircuit, GarbledGate, Garbler, GarblingScheme};
 pub use key::Key;
+pub use stats::{CircuitStats, SchemeCost};
 pub use two_party::{execute_secure_protocol, Alice, Bob};
+pub use yosys::PortAssignment;
@@ -1,7 +1,16 @@
-use garbled_circuit_rs::{execute_secure_protocol, Circuit};
+use garbled_circuit_rs::simulate::{Simulator, Vectors};
+use garbled_circuit_rs::{execute_secure_protocol, Circuit, Equivalence, PortAssignment};
 use std::collections::HashMap;
 use std::env;
+use std::fs;
 use std::path::Path;
+use std::time::Instant;
+
+/// Most inputs `simulate` enumerates every vector of
+const EXHAUSTIVE_INPUTS: usize = 24;
+
+/// Random vectors `simulate` checks when there are more inputs
+const DEFAULT_VECTORS: u64 = 1 << 22;
 
 fn parse_binary_string(s: &str) -> Result<Vec<u8>, String> {
     s.chars()
@@ -14,17 +23,57 @@ fn parse_binary_string(s: &str) -> Result<Vec<u8>, String> {
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    // `stats`, `simulate` and `equiv` report on circuits instead of running
+    // them
+    let command = match args.get(1).map(String::as_str) {
+        Some(command @ ("stats" | "simulate" | "equiv")) => Some(command.to_string()),
+        _ => None,
+    };
+    if command.is_some() {
+        args.remove(1);
+    }
 
-    if args.len() < 2 {
+    if command.as_deref() == Some("equiv") && args.len() == 5 {
+        let left = load_circuit(&args[1], Some(&args[2]));
+        let right = load_circuit(&args[3], Some(&args[4]));
+        check_equivalence(&left, &right);
+        return;
+    }
+
+    if args.len() < 2 || command.as_deref() == Some("equiv") {
         eprintln!(
-            "Usage: {} [circuit_file.json] <circuit_index> [alice_input] [bob_input]",
+            "Usage: {} [circuit_file] <circuit_index> [alice_input] [bob_input]",
             args[0]
         );
-        eprintln!("  circuit_file.json: Optional JSON file containing circuits (default: circuits/bool.json)");
+        eprintln!(
+            "  circuit_file: Optional JSON file containing circuits (default: circuits/bool.json),"
+        );
+        eprintln!("                or a Bristol or Bristol Fashion file (.txt or .bristol) holding circuit 0,");
+        eprintln!("                or a Yosys JSON netlist, AIGER file (.aag or .aig) or BLIF file (.blif)");
+        eprintln!(
+            "                holding circuit 0, or an expression file (.expr) holding circuit 0"
+        );
         eprintln!("  circuit_index: 0-based index of the circuit to evaluate");
         eprintln!("  alice_input: Binary string for Alice's input (e.g., '10' for inputs 1,0)");
         eprintln!("  bob_input: Binary string for Bob's input (e.g., '1' for input 1)");
+        eprintln!("\n       {} stats [circuit_file] <circuit_index>", args[0]);
+        eprintln!("  Report gate counts, depths, fan-out and garbling costs of a circuit");
+        eprintln!(
+            "\n       {} simulate [circuit_file] <circuit_index> [vector_count]",
+            args[0]
+        );
+        eprintln!("  Check the optimized circuit against the circuit in the clear, on every");
+        eprintln!(
+            "  input vector up to {EXHAUSTIVE_INPUTS} inputs or else on vector_count random ones"
+        );
+        eprintln!(
+            "\n       {} equiv <circuit_file> <circuit_index> <circuit_file> <circuit_index>",
+            args[0]
+        );
+        eprintln!("  Check that two circuits compute the same function, or print an input");
+        eprintln!("  on which they differ");
         eprintln!("\nNote: All circuits are evaluated using secure Oblivious Transfer protocol");
         eprintln!("\nExamples:");
         eprintln!(
@@ -35,56 +84,58 @@ fn main() {
             "  {} circuits/max.json 0 11 00 # Run max circuit with custom file",
             args[0]
         );
+        eprintln!(
+            "  {} stats circuits/max.json 0 # Report on the max circuit",
+            args[0]
+        );
+        eprintln!(
+            "  {} simulate circuits/max.json 0 # Check the max circuit on every input",
+            args[0]
+        );
+        eprintln!(
+            "  {} equiv circuits/bool.json 0 circuits/bool.json 1 # Compare AND with OR",
+            args[0]
+        );
         std::process::exit(1);
     }
 
-    // Check if first argument is a JSON file
-    let (json_file, start_arg_idx) = if args.len() > 1 && args[1].ends_with(".json") {
+    // Check if first argument is a circuit file
+    let (circuit_file, start_arg_idx) = if is_circuit_file(&args[1]) {
         (args[1].clone(), 2)
     } else {
         ("circuits/bool.json".to_string(), 1)
     };
+    let circuit = load_circuit(&circuit_file, args.get(start_arg_idx));
 
-    // Parse circuit index
-    let circuit_index: usize = if start_arg_idx < args.len() {
-        match args[start_arg_idx].parse() {
-            Ok(idx) => idx,
-            Err(_) => {
-                eprintln!("Error: Invalid circuit index '{}'", args[start_arg_idx]);
-                std::process::exit(1);
-            }
+    match command.as_deref() {
+        Some("stats") => {
+            print_stats(&circuit);
+            return;
         }
-    } else {
-        eprintln!("Error: Missing circuit index");
-        std::process::exit(1);
-    };
+        Some("simulate") => {
+            let count = match args.get(start_arg_idx + 1).map(|arg| arg.parse()) {
+                None => None,
+                Some(Ok(count)) => Some(count),
+                Some(Err(_)) => {
+                    eprintln!("Error: Invalid vector count '{}'", args[start_arg_idx + 1]);
+                    std::process::exit(1);
+                }
+            };
+            simulate(&circuit, count);
+            return;
+        }
+        _ => {}
+    }
 
-    // Load circuits from JSON file
-    let json_path = Path::new(&json_file);
-    let circuits = match Circuit::from_json_file(json_path) {
-        Ok(circuits) => circuits,
+    // Optimize before garbling, keeping the input and output wires
+    let circuit = match circuit.optimize() {
+        Ok(circuit) => circuit,
         Err(e) => {
-            eprintln!("Error: Failed to load {}: {}", json_path.display(), e);
+            eprintln!("Error: {e}");
             std::process::exit(1);
         }
     };
 
-    // Check if circuit index is valid
-    if circuit_index >= circuits.len() {
-        eprintln!(
-            "Error: Circuit index {} is out of range. Available circuits: 0-{}",
-            circuit_index,
-            circuits.len() - 1
-        );
-        eprintln!("\nAvailable circuits:");
-        for (i, circuit) in circuits.iter().enumerate() {
-            eprintln!("  {}: {}", i, circuit.id);
-        }
-        std::process::exit(1);
-    }
-
-    let circuit = &circuits[circuit_index];
-
     // Parse Alice's input if provided
     let alice_input = if args.len() > start_arg_idx + 1 {
         match parse_binary_string(&args[start_arg_idx + 1]) {
@@ -121,8 +172,13 @@ fn main() {
     let bob_inputs = prepare_party_inputs(&bob_input, bob_wires, "Bob");
 
     // Always use secure OT protocol
-    let result = execute_secure_protocol(circuit.clone(), alice_inputs, bob_inputs)
-        .expect("Failed to execute secure protocol");
+    let result = match execute_secure_protocol(circuit.clone(), alice_inputs, bob_inputs) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
 
     // Print Alice inputs
     if !alice_wires.is_empty() {
@@ -153,6 +209,221 @@ fn main() {
     println!();
 }
 
+/// Print the statistics of a circuit as loaded and as optimized for garbling
+fn print_stats(circuit: &Circuit) {
+    let report = circuit.stats().and_then(|stats| {
+        let optimized = circuit.optimize()?.stats()?;
+        Ok((stats, optimized))
+    });
+    match report {
+        Ok((stats, optimized)) => {
+            println!("{stats}");
+            println!("After optimization:");
+            print!("{optimized}");
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Simulate a circuit and its optimized form on the same input vectors,
+/// every vector up to `EXHAUSTIVE_INPUTS` inputs unless a count is given,
+/// and report the first vector they disagree on
+fn simulate(circuit: &Circuit, count: Option<u64>) {
+    let simulators = Simulator::new(circuit).and_then(|original| {
+        let optimized = Simulator::new(&circuit.optimize()?)?;
+        Ok((original, optimized))
+    });
+    let (original, optimized) = match simulators {
+        Ok(simulators) => simulators,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    let vectors = match count {
+        None if original.input_count() <= EXHAUSTIVE_INPUTS => Vectors::Exhaustive,
+        count => Vectors::Random {
+            count: count.unwrap_or(DEFAULT_VECTORS),
+            seed: rand::random(),
+        },
+    };
+
+    let start = Instant::now();
+    let mut simulated = 0;
+    let mut mismatch = None;
+    original.simulate::<u128>(vectors, |batch| {
+        let outputs = optimized.run(&batch.inputs);
+        if let Some(lane) = batch.first_difference(&outputs) {
+            mismatch = Some((batch.input_bits(lane), batch.output_bits(lane)));
+        }
+        simulated += batch.lanes as u64;
+        mismatch.is_none()
+    });
+    let kind = match vectors {
+        Vectors::Exhaustive => "exhaustive",
+        Vectors::Random { .. } => "random",
+    };
+    println!(
+        "Simulated {simulated} {kind} input vectors of {} in {:.3} s",
+        circuit.id,
+        start.elapsed().as_secs_f64()
+    );
+    if let Some((inputs, outputs)) = mismatch {
+        let bits = |bits: &[u8]| bits.iter().map(|bit| bit.to_string()).collect::<String>();
+        let alice = circuit.alice.as_ref().map_or(0, Vec::len);
+        eprintln!(
+            "Error: The optimized circuit differs for Alice={} Bob={}, where the circuit outputs {}",
+            bits(&inputs[..alice]),
+            bits(&inputs[alice..]),
+            bits(&outputs)
+        );
+        std::process::exit(1);
+    }
+    println!("The optimized circuit agrees on every vector");
+}
+
+/// Check two circuits for equivalence and print a counterexample if they
+/// differ
+fn check_equivalence(left: &Circuit, right: &Circuit) {
+    let start = Instant::now();
+    let equivalence = match left.check_equivalence(right) {
+        Ok(equivalence) => equivalence,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+    match equivalence {
+        Equivalence::Equivalent => {
+            println!(
+                "{} and {} are equivalent ({elapsed:.3} s)",
+                left.id, right.id
+            )
+        }
+        Equivalence::Different(counterexample) => {
+            let bits = |bits: &[u8]| bits.iter().map(|bit| bit.to_string()).collect::<String>();
+            println!(
+                "{} and {} differ for Alice={} Bob={}: {} outputs {} and {} outputs {}",
+                left.id,
+                right.id,
+                bits(&counterexample.alice),
+                bits(&counterexample.bob),
+                left.id,
+                bits(&counterexample.left),
+                right.id,
+                bits(&counterexample.right)
+            );
+            std::process::exit(1);
+        }
+        Equivalence::Unknown { vectors } => {
+            println!(
+                "{} and {} agree on {vectors} random input vectors, but are too large to prove equivalent",
+                left.id, right.id
+            );
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Whether an argument names a circuit file rather than a circuit index
+fn is_circuit_file(arg: &str) -> bool {
+    [
+        ".json", ".txt", ".bristol", ".aag", ".aig", ".blif", ".expr",
+    ]
+    .iter()
+    .any(|extension| arg.ends_with(extension))
+}
+
+/// Load one circuit of a JSON, Bristol Fashion, AIGER, BLIF or expression
+/// file, exiting with an error if it fails or the index is missing or out of
+/// range
+fn load_circuit(circuit_file: &str, index: Option<&String>) -> Circuit {
+    // Parse circuit index
+    let circuit_index: usize = match index.map(|index| (index, index.parse())) {
+        Some((_, Ok(idx))) => idx,
+        Some((index, Err(_))) => {
+            eprintln!("Error: Invalid circuit index '{index}'");
+            std::process::exit(1);
+        }
+        None => {
+            eprintln!("Error: Missing circuit index");
+            std::process::exit(1);
+        }
+    };
+
+    let circuit_path = Path::new(circuit_file);
+    let is_bristol = circuit_file.ends_with(".txt") || circuit_file.ends_with(".bristol");
+    let is_aiger = circuit_file.ends_with(".aag") || circuit_file.ends_with(".aig");
+    let loaded = if is_bristol {
+        Circuit::load_bristol(circuit_path).map(|circuit| vec![circuit])
+    } else if circuit_file.ends_with(".expr") {
+        Circuit::load_expression(circuit_path).map(|circuit| vec![circuit])
+    } else if is_aiger {
+        load_ports(circuit_path)
+            .and_then(|ports| Circuit::load_aiger(circuit_path, &ports))
+            .map(|circuit| vec![circuit])
+    } else if circuit_file.ends_with(".blif") {
+        load_ports(circuit_path)
+            .and_then(|ports| Circuit::load_blif(circuit_path, &ports))
+            .map(|circuit| vec![circuit])
+    } else if is_yosys_netlist(circuit_path) {
+        load_ports(circuit_path)
+            .and_then(|ports| Circuit::load_yosys_json(circuit_path, &ports))
+            .map(|circuit| vec![circuit])
+    } else {
+        Circuit::from_json_file(circuit_path)
+    };
+    let mut circuits = match loaded {
+        Ok(circuits) => circuits,
+        Err(e) => {
+            eprintln!("Error: Failed to load {}: {}", circuit_path.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    // Check if circuit index is valid
+    if circuit_index >= circuits.len() {
+        eprintln!(
+            "Error: Circuit index {} is out of range. Available circuits: 0-{}",
+            circuit_index,
+            circuits.len() - 1
+        );
+        eprintln!("\nAvailable circuits:");
+        for (i, circuit) in circuits.iter().enumerate() {
+            eprintln!("  {}: {}", i, circuit.id);
+        }
+        std::process::exit(1);
+    }
+    let circuit = circuits.swap_remove(circuit_index);
+    for wire in circuit.unused_inputs() {
+        eprintln!("Warning: Input wire {wire} of {} is never used", circuit.id);
+    }
+    circuit
+}
+
+/// Port assignment from the file next to a netlist, such as max.ports.json
+/// for max.json, or assignment by name when there is none
+fn load_ports(circuit_path: &Path) -> garbled_circuit_rs::Result<PortAssignment> {
+    let ports_path = circuit_path.with_extension("ports.json");
+    if ports_path.exists() {
+        PortAssignment::from_json_file(&ports_path)
+    } else {
+        Ok(PortAssignment::default())
+    }
+}
+
+/// Whether a JSON file is a Yosys netlist rather than a circuit collection
+fn is_yosys_netlist(path: &Path) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+        .is_some_and(|value| value.get("modules").is_some())
+}
+
 /// Parse and validate input bits for a party's wires
 fn prepare_party_inputs(
     input_bits: &Option<Vec<u8>>,
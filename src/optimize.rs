@@ -0,0 +1,787 @@
+use crate::circuit::{Circuit, Gate, GateType};
+use crate::error::Result;
+use crate::garbled::GarblingScheme;
+use std::collections::{HashMap, HashSet};
+
+/// Optimization run by `Circuit::optimize_with`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pass {
+    /// Fold constant inputs into the gates they feed, also dropping repeated
+    /// inputs, inputs a gate ignores and buffers
+    FoldConstants,
+    /// Remove double negations and absorb NOT gates into the gates they feed
+    CollapseNots,
+    /// Merge gates computing the same function of the same inputs
+    MergeDuplicates,
+    /// Rebuild small cones of gates with fewer AND-like gates and free XORs
+    RewriteAnds,
+    /// Remove gates no output depends on
+    RemoveDeadGates,
+}
+
+impl Pass {
+    /// Every pass, in the order `Circuit::optimize` runs them
+    pub const ALL: [Pass; 5] = [
+        Pass::FoldConstants,
+        Pass::CollapseNots,
+        Pass::MergeDuplicates,
+        Pass::RewriteAnds,
+        Pass::RemoveDeadGates,
+    ];
+}
+
+/// Most leaves of a cone `Pass::RewriteAnds` rebuilds
+const MAX_CONE_LEAVES: usize = 4;
+
+/// Most gates below the root of a cone `Pass::RewriteAnds` rebuilds
+const MAX_CONE_GATES: usize = 16;
+
+/// Whether a gate type needs no garbled table when XOR is free
+fn is_free(gate_type: GateType) -> bool {
+    GarblingScheme::FreeXor.is_free(gate_type)
+}
+
+/// Value of a wire once gates are replaced: a wire or a constant
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Signal {
+    Wire(u32),
+    Constant(u8),
+}
+
+/// What a pass turns a gate into
+enum Replacement {
+    Gate(Gate),
+    Signal(Signal),
+}
+
+/// Table over one input fewer, where input `position` of `arity` takes the
+/// bit `bit` returns for each row of the smaller table
+fn remove_input(
+    table: &[u8],
+    arity: usize,
+    position: usize,
+    bit: impl Fn(usize) -> usize,
+) -> Vec<u8> {
+    let shift = arity - 1 - position;
+    (0..1usize << (arity - 1))
+        .map(|row| {
+            let low = row & ((1 << shift) - 1);
+            table[((row >> shift) << (shift + 1)) | (bit(row) << shift) | low]
+        })
+        .collect()
+}
+
+/// Table with input `position` of `arity` negated
+fn negate_input(table: &[u8], arity: usize, position: usize) -> Vec<u8> {
+    let shift = arity - 1 - position;
+    (0..table.len())
+        .map(|row| table[row ^ (1 << shift)])
+        .collect()
+}
+
+/// Bits of the affine function selecting the leaves in `mask`, one per row
+fn parity_table(mask: usize, rows: usize) -> u16 {
+    (0..rows).fold(0, |table, row| {
+        table | ((((row & mask).count_ones() & 1) as u16) << row)
+    })
+}
+
+/// Mask and constant writing `table` as an XOR of leaves, if it is affine
+fn affine_mask(table: u16, rows: usize) -> Option<(usize, u8)> {
+    let full = ((1u32 << rows) - 1) as u16;
+    (0..rows).find_map(|mask| {
+        let parity = parity_table(mask, rows);
+        if table == parity {
+            Some((mask, 0))
+        } else if table == parity ^ full {
+            Some((mask, 1))
+        } else {
+            None
+        }
+    })
+}
+
+/// Circuit under optimization, its gates in topological order
+#[derive(Clone)]
+struct Netlist {
+    gates: Vec<Gate>,
+    constants: HashMap<u32, u8>,
+    outputs: Vec<Signal>,
+    next_wire: u32,
+}
+
+impl Netlist {
+    fn new(circuit: &Circuit) -> Result<Netlist> {
+        let gates: Vec<Gate> = circuit.topological_order()?.into_iter().cloned().collect();
+        let next_wire = gates
+            .iter()
+            .map(|gate| gate.id)
+            .chain(circuit.input_wires())
+            .chain(circuit.constants.keys().copied())
+            .chain(circuit.out.iter().copied())
+            .max()
+            .map_or(0, |wire| wire + 1);
+        let mut netlist = Netlist {
+            gates,
+            constants: circuit.constants.clone(),
+            outputs: Vec::new(),
+            next_wire,
+        };
+        netlist.outputs = circuit
+            .out
+            .iter()
+            .map(|&wire| netlist.signal(&HashMap::new(), wire))
+            .collect();
+        Ok(netlist)
+    }
+
+    /// Gates needing a garbled table, then all gates
+    fn cost(&self) -> (usize, usize) {
+        let tables = self
+            .gates
+            .iter()
+            .filter(|gate| !is_free(gate.gate_type))
+            .count();
+        (tables, self.gates.len())
+    }
+
+    fn fresh_wire(&mut self) -> u32 {
+        self.next_wire += 1;
+        self.next_wire - 1
+    }
+
+    fn signal(&self, replaced: &HashMap<u32, Signal>, wire: u32) -> Signal {
+        match (replaced.get(&wire), self.constants.get(&wire)) {
+            (Some(&signal), _) => signal,
+            (None, Some(&value)) => Signal::Constant(value),
+            (None, None) => Signal::Wire(wire),
+        }
+    }
+
+    /// Wire carrying a signal, adding a constant wire if there is none
+    fn wire(&mut self, signal: Signal) -> u32 {
+        match signal {
+            Signal::Wire(wire) => wire,
+            Signal::Constant(value) => {
+                let existing = self
+                    .constants
+                    .iter()
+                    .find(|(_, &constant)| constant == value)
+                    .map(|(&wire, _)| wire);
+                existing.unwrap_or_else(|| {
+                    let wire = self.fresh_wire();
+                    self.constants.insert(wire, value);
+                    wire
+                })
+            }
+        }
+    }
+
+    /// Rebuild the gates in order, their inputs following the replacements
+    /// made so far, letting `replace` decide what each gate becomes
+    ///
+    /// `replace` may push extra gates, which then come before the gate.
+    fn rewrite(&mut self, mut replace: impl FnMut(&mut Netlist, Gate) -> Replacement) {
+        let mut replaced: HashMap<u32, Signal> = HashMap::new();
+        for mut gate in std::mem::take(&mut self.gates) {
+            for input in 0..gate.inputs.len() {
+                let signal = self.signal(&replaced, gate.inputs[input]);
+                gate.inputs[input] = self.wire(signal);
+            }
+            let id = gate.id;
+            match replace(self, gate) {
+                Replacement::Gate(gate) => self.gates.push(gate),
+                Replacement::Signal(signal) => {
+                    replaced.insert(id, signal);
+                }
+            }
+        }
+        self.outputs = std::mem::take(&mut self.outputs)
+            .into_iter()
+            .map(|signal| match signal {
+                Signal::Wire(wire) => self.signal(&replaced, wire),
+                constant => constant,
+            })
+            .collect();
+    }
+
+    /// Gate computing `table` over `inputs`, without constant, repeated or
+    /// ignored inputs, or the signal it reduces to
+    ///
+    /// Removing an input can leave an earlier one ignored, so the scan starts
+    /// over after every removal.
+    fn simplify(&self, id: u32, mut inputs: Vec<u32>, mut table: Vec<u8>) -> Replacement {
+        let mut position = 0;
+        while position < inputs.len() {
+            let arity = inputs.len();
+            let shift = arity - 1 - position;
+            let repeated = inputs[..position]
+                .iter()
+                .position(|&wire| wire == inputs[position]);
+            let ignored = (0..table.len()).all(|row| table[row] == table[row ^ (1 << shift)]);
+            table = if let Some(&value) = self.constants.get(&inputs[position]) {
+                remove_input(&table, arity, position, |_| value as usize)
+            } else if let Some(first) = repeated {
+                remove_input(&table, arity, position, |row| {
+                    (row >> (arity - 2 - first)) & 1
+                })
+            } else if ignored {
+                remove_input(&table, arity, position, |_| 0)
+            } else {
+                position += 1;
+                continue;
+            };
+            inputs.remove(position);
+            position = 0;
+        }
+
+        if table.iter().all(|&bit| bit == table[0]) {
+            return Replacement::Signal(Signal::Constant(table[0]));
+        }
+        if table == [0, 1] {
+            return Replacement::Signal(Signal::Wire(inputs[0]));
+        }
+        let gate_type = match inputs.len() {
+            arity @ (1 | 2) => {
+                let packed = table
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |packed, (row, &bit)| packed | bit << row);
+                GateType::from_truth_table(arity, packed)
+            }
+            _ => None,
+        };
+        match gate_type {
+            Some(gate_type) => Replacement::Gate(Gate::new(id, gate_type, inputs)),
+            None => Replacement::Gate(Gate::lut(id, inputs, table)),
+        }
+    }
+
+    fn fold_constants(&mut self) {
+        self.rewrite(|netlist, gate| {
+            let table = gate.truth_table();
+            netlist.simplify(gate.id, gate.inputs, table)
+        });
+    }
+
+    fn collapse_nots(&mut self) {
+        // Input of every NOT gate kept so far
+        let mut negated: HashMap<u32, u32> = HashMap::new();
+        self.rewrite(|netlist, gate| {
+            if !gate.inputs.iter().any(|wire| negated.contains_key(wire)) {
+                if gate.gate_type == GateType::Not {
+                    negated.insert(gate.id, gate.inputs[0]);
+                }
+                return Replacement::Gate(gate);
+            }
+            if gate.gate_type == GateType::Not {
+                return Replacement::Signal(Signal::Wire(negated[&gate.inputs[0]]));
+            }
+
+            let arity = gate.inputs.len();
+            let mut table = gate.truth_table();
+            let mut inputs = gate.inputs;
+            for (position, input) in inputs.iter_mut().enumerate() {
+                if let Some(&wire) = negated.get(input) {
+                    table = negate_input(&table, arity, position);
+                    *input = wire;
+                }
+            }
+            let replacement = netlist.simplify(gate.id, inputs, table);
+            if let Replacement::Gate(gate) = &replacement {
+                if gate.gate_type == GateType::Not {
+                    negated.insert(gate.id, gate.inputs[0]);
+                }
+            }
+            replacement
+        });
+    }
+
+    fn merge_duplicates(&mut self) {
+        let mut seen: HashMap<(Vec<u32>, Vec<u8>), u32> = HashMap::new();
+        self.rewrite(|_, gate| {
+            let table = gate.truth_table();
+            let mut inputs = gate.inputs.clone();
+            // Inputs of symmetric two-input gates are interchangeable
+            if inputs.len() == 2 && table[1] == table[2] {
+                inputs.sort_unstable();
+            }
+            match seen.get(&(inputs.clone(), table.clone())) {
+                Some(&wire) => Replacement::Signal(Signal::Wire(wire)),
+                None => {
+                    seen.insert((inputs, table), gate.id);
+                    Replacement::Gate(gate)
+                }
+            }
+        });
+    }
+
+    /// Wires some output depends on
+    fn live_wires(&self) -> HashSet<u32> {
+        let mut live: HashSet<u32> = self
+            .outputs
+            .iter()
+            .filter_map(|signal| match signal {
+                Signal::Wire(wire) => Some(*wire),
+                Signal::Constant(_) => None,
+            })
+            .collect();
+        for gate in self.gates.iter().rev() {
+            if live.contains(&gate.id) {
+                live.extend(&gate.inputs);
+            }
+        }
+        live
+    }
+
+    fn remove_dead_gates(&mut self) {
+        let live = self.live_wires();
+        self.gates.retain(|gate| live.contains(&gate.id));
+        self.constants.retain(|wire, _| live.contains(wire));
+    }
+
+    fn rewrite_ands(&mut self) {
+        // Uses of every wire by live gates and outputs, and the last gate
+        // using it, which is its only one when it has a single use
+        let live = self.live_wires();
+        let mut fanout: HashMap<u32, usize> = HashMap::new();
+        let mut users: HashMap<u32, u32> = HashMap::new();
+        for gate in self.gates.iter().filter(|gate| live.contains(&gate.id)) {
+            for &wire in &gate.inputs {
+                *fanout.entry(wire).or_default() += 1;
+                users.insert(wire, gate.id);
+            }
+        }
+        for signal in &self.outputs {
+            if let Signal::Wire(wire) = signal {
+                *fanout.entry(*wire).or_default() += 1;
+            }
+        }
+
+        let mut drivers: HashMap<u32, usize> = HashMap::new();
+        self.rewrite(|netlist, gate| {
+            let replacement = if is_free(gate.gate_type) {
+                Replacement::Gate(gate)
+            } else {
+                netlist.rewrite_cone(gate, &drivers, &fanout, &users)
+            };
+            if let Replacement::Gate(gate) = &replacement {
+                drivers.insert(gate.id, netlist.gates.len());
+            }
+            replacement
+        });
+    }
+
+    /// Replacement of a gate needing a table, rebuilding the cone of gates
+    /// it alone depends on with fewer such gates when possible
+    ///
+    /// The cone grows from the gate through free gates and through gates
+    /// only the cone uses, up to `MAX_CONE_LEAVES` leaves. Its function of
+    /// the leaves is rebuilt as XORs of leaves, or as one AND-like gate of
+    /// two such XORs, XORed with a third.
+    fn rewrite_cone(
+        &mut self,
+        root: Gate,
+        drivers: &HashMap<u32, usize>,
+        fanout: &HashMap<u32, usize>,
+        users: &HashMap<u32, u32>,
+    ) -> Replacement {
+        let mut leaves: Vec<u32> = Vec::new();
+        for &wire in &root.inputs {
+            if !leaves.contains(&wire) {
+                leaves.push(wire);
+            }
+        }
+        if leaves.len() > MAX_CONE_LEAVES {
+            return Replacement::Gate(root);
+        }
+
+        // Grow the cone, counting the gates needing tables that die with it
+        let mut cone: Vec<usize> = Vec::new();
+        let mut inside: HashSet<u32> = HashSet::from([root.id]);
+        let mut owned: HashSet<u32> = HashSet::from([root.id]);
+        let mut cost = 1;
+        while cone.len() < MAX_CONE_GATES {
+            let expansion = leaves.iter().enumerate().find_map(|(position, leaf)| {
+                let &index = drivers.get(leaf)?;
+                let driver = &self.gates[index];
+                let owns = fanout.get(leaf) == Some(&1)
+                    && users.get(leaf).is_some_and(|user| owned.contains(user));
+                let free = is_free(driver.gate_type);
+                let mut grown = leaves.clone();
+                grown.remove(position);
+                for &wire in &driver.inputs {
+                    if !grown.contains(&wire) && !inside.contains(&wire) {
+                        grown.push(wire);
+                    }
+                }
+                ((free || owns) && grown.len() <= MAX_CONE_LEAVES)
+                    .then_some((index, grown, owns, free))
+            });
+            let Some((index, grown, owns, free)) = expansion else {
+                break;
+            };
+            inside.insert(self.gates[index].id);
+            if owns {
+                owned.insert(self.gates[index].id);
+                cost += usize::from(!free);
+            }
+            cone.push(index);
+            leaves = grown;
+        }
+        cone.sort_unstable();
+
+        // Function of the leaves, the first leaf being the most significant
+        // bit of the row index
+        let arity = leaves.len();
+        let rows = 1 << arity;
+        let function = (0..rows).fold(0u16, |function, row| {
+            let mut values: HashMap<u32, u8> = leaves
+                .iter()
+                .enumerate()
+                .map(|(i, &leaf)| (leaf, ((row >> (arity - 1 - i)) & 1) as u8))
+                .collect();
+            for gate in cone.iter().map(|&index| &self.gates[index]).chain([&root]) {
+                let bits: Vec<u8> = gate.inputs.iter().map(|wire| values[wire]).collect();
+                values.insert(gate.id, gate.eval(&bits));
+            }
+            function | (values[&root.id] as u16) << row
+        });
+
+        if let Some((mask, constant)) = affine_mask(function, rows) {
+            return self.emit_affine(root.id, &leaves, mask, constant);
+        }
+        if cost < 2 {
+            return Replacement::Gate(root);
+        }
+        let negate = |bit: u8| if bit == 1 { u16::MAX } else { 0 };
+        for left in 1..rows {
+            for right in left + 1..rows {
+                for alpha in 0..2 {
+                    for beta in 0..2 {
+                        let product = (parity_table(left, rows) ^ negate(alpha))
+                            & (parity_table(right, rows) ^ negate(beta));
+                        let Some((mask, gamma)) = affine_mask(function ^ product, rows) else {
+                            continue;
+                        };
+                        let and_table = (0..4).fold(0u8, |table, row| {
+                            let bit = ((row >> 1) ^ alpha) & ((row & 1) ^ beta);
+                            table | (bit ^ gamma) << row
+                        });
+                        let gate_type = GateType::from_truth_table(2, and_table).unwrap();
+                        let inputs = vec![self.xor_of(&leaves, left), self.xor_of(&leaves, right)];
+                        if mask == 0 {
+                            return Replacement::Gate(Gate::new(root.id, gate_type, inputs));
+                        }
+                        let product = self.fresh_wire();
+                        self.gates.push(Gate::new(product, gate_type, inputs));
+                        let rest = self.xor_of(&leaves, mask);
+                        return Replacement::Gate(Gate::new(
+                            root.id,
+                            GateType::Xor,
+                            vec![product, rest],
+                        ));
+                    }
+                }
+            }
+        }
+        Replacement::Gate(root)
+    }
+
+    /// Wire carrying the XOR of the leaves selected by a nonzero mask, adding
+    /// XOR gates as needed
+    fn xor_of(&mut self, leaves: &[u32], mask: usize) -> u32 {
+        let arity = leaves.len();
+        let mut selected = (0..arity)
+            .filter(|i| (mask >> (arity - 1 - i)) & 1 == 1)
+            .map(|i| leaves[i]);
+        let first = selected.next().expect("mask selects no leaf");
+        selected.fold(first, |sum, leaf| {
+            let wire = self.fresh_wire();
+            self.gates
+                .push(Gate::new(wire, GateType::Xor, vec![sum, leaf]));
+            wire
+        })
+    }
+
+    /// Replacement of gate `id` by the XOR of the leaves in `mask` and a
+    /// constant, needing no table
+    fn emit_affine(&mut self, id: u32, leaves: &[u32], mask: usize, constant: u8) -> Replacement {
+        let arity = leaves.len();
+        if mask == 0 {
+            return Replacement::Signal(Signal::Constant(constant));
+        }
+        let last = (0..arity)
+            .rev()
+            .find(|i| (mask >> (arity - 1 - i)) & 1 == 1)
+            .unwrap();
+        let rest = mask & !(1 << (arity - 1 - last));
+        match (rest, constant) {
+            (0, 0) => Replacement::Signal(Signal::Wire(leaves[last])),
+            (0, _) => Replacement::Gate(Gate::new(id, GateType::Not, vec![leaves[last]])),
+            _ => {
+                let gate_type = if constant == 1 {
+                    GateType::Xnor
+                } else {
+                    GateType::Xor
+                };
+                let sum = self.xor_of(leaves, rest);
+                Replacement::Gate(Gate::new(id, gate_type, vec![sum, leaves[last]]))
+            }
+        }
+    }
+
+    fn run(&mut self, pass: Pass) {
+        match pass {
+            Pass::FoldConstants => self.fold_constants(),
+            Pass::CollapseNots => self.collapse_nots(),
+            Pass::MergeDuplicates => self.merge_duplicates(),
+            Pass::RewriteAnds => self.rewrite_ands(),
+            Pass::RemoveDeadGates => self.remove_dead_gates(),
+        }
+    }
+
+    /// Circuit of the optimized gates, with the inputs and outputs of the
+    /// original
+    ///
+    /// Outputs that became another wire get a buffer, and outputs that
+    /// became constant get a constant wire.
+    fn into_circuit(self, circuit: &Circuit) -> Circuit {
+        let mut gates = self.gates;
+        let mut constants = self.constants;
+        let mut buffered = HashSet::new();
+        for (&wire, signal) in circuit.out.iter().zip(self.outputs) {
+            match signal {
+                Signal::Wire(driver) if driver == wire => {}
+                Signal::Wire(driver) => {
+                    if buffered.insert(wire) {
+                        gates.push(Gate::new(wire, GateType::Buf, vec![driver]));
+                    }
+                }
+                Signal::Constant(value) => {
+                    constants.insert(wire, value);
+                }
+            }
+        }
+        Circuit {
+            id: circuit.id.clone(),
+            gates,
+            alice: circuit.alice.clone(),
+            bob: circuit.bob.clone(),
+            out: circuit.out.clone(),
+            constants,
+        }
+    }
+}
+
+impl Circuit {
+    /// Equivalent circuit with fewer gates, running every pass
+    pub fn optimize(&self) -> Result<Circuit> {
+        self.optimize_with(&Pass::ALL)
+    }
+
+    /// Equivalent circuit after running the passes in order, repeatedly while
+    /// a round removes gates needing garbled tables or, failing that, gates
+    ///
+    /// Party inputs and outputs keep their wires, so inputs and outputs of
+    /// both circuits line up. Inputs the outputs no longer depend on stay and
+    /// show up in `unused_inputs`. Fails if the circuit is not well formed.
+    pub fn optimize_with(&self, passes: &[Pass]) -> Result<Circuit> {
+        let mut netlist = Netlist::new(self)?;
+        let mut best = self.clone();
+        let mut cost = netlist.cost();
+        loop {
+            for &pass in passes {
+                netlist.run(pass);
+            }
+            let round_cost = netlist.cost();
+            if round_cost >= cost {
+                return Ok(best);
+            }
+            cost = round_cost;
+            best = netlist.clone().into_circuit(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{Bit, CircuitBuilder};
+    use crate::gadgets::{self, Variant};
+    use crate::test_support::{assert_equivalent, round_trip_circuits};
+
+    /// Gates needing a garbled table
+    fn table_count(circuit: &Circuit) -> usize {
+        circuit
+            .gates
+            .iter()
+            .filter(|gate| !is_free(gate.gate_type))
+            .count()
+    }
+
+    fn circuit(gates: Vec<Gate>, out: Vec<u32>) -> Circuit {
+        Circuit {
+            id: "optimize".to_string(),
+            gates,
+            alice: Some(vec![0, 1]),
+            bob: Some(vec![2]),
+            out,
+            constants: HashMap::from([(3, 1)]),
+        }
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let original = circuit(
+            vec![
+                // 4 = 0 & 1, 5 = 4 ^ 1 = !4, 6 = 2 | 2 = 2
+                Gate::new(4, GateType::And, vec![0, 3]),
+                Gate::new(5, GateType::Xor, vec![4, 3]),
+                Gate::new(6, GateType::Or, vec![2, 2]),
+                Gate::new(7, GateType::ProjA, vec![5, 1]),
+                Gate::new(8, GateType::Nand, vec![3, 3]),
+                Gate::lut(9, vec![0, 1, 3], vec![0, 1, 1, 0, 0, 0, 0, 1]),
+            ],
+            vec![7, 6, 8, 9],
+        );
+        let optimized = original.optimize_with(&[Pass::FoldConstants]).unwrap();
+        assert_equivalent(&original, &optimized);
+        let types: Vec<GateType> = optimized.gates.iter().map(|gate| gate.gate_type).collect();
+        assert_eq!(
+            types,
+            vec![GateType::Not, GateType::Xnor, GateType::Buf, GateType::Buf]
+        );
+        assert_eq!(optimized.constants.get(&8), Some(&0));
+    }
+
+    #[test]
+    fn test_fold_constants_to_constant() {
+        // Folding the constant makes the other input of each gate ignored
+        let zero = Gate::new(5, GateType::Zero, vec![]);
+        let original = Circuit {
+            constants: HashMap::from([(3, 1), (4, 0)]),
+            ..circuit(
+                vec![
+                    Gate::new(6, GateType::And, vec![0, 4]),
+                    Gate::new(7, GateType::Or, vec![1, 3]),
+                    zero,
+                    Gate::new(8, GateType::And, vec![2, 5]),
+                    Gate::new(9, GateType::Or, vec![6, 2]),
+                ],
+                vec![6, 7, 8, 9],
+            )
+        };
+        let optimized = original.optimize().unwrap();
+        assert_equivalent(&original, &optimized);
+        assert_eq!(table_count(&optimized), 0);
+
+        // The divider pads its partial remainders with constant zeros
+        let builder = CircuitBuilder::new();
+        let x = builder.alice_uint::<4>();
+        let y = builder.bob_uint::<4>();
+        builder.output_uint(&(&x / &y));
+        let division = builder.build("division").unwrap();
+        assert_equivalent(&division, &division.optimize().unwrap());
+    }
+
+    #[test]
+    fn test_collapse_and_merge() {
+        let original = circuit(
+            vec![
+                Gate::new(4, GateType::Not, vec![0]),
+                Gate::new(5, GateType::Not, vec![4]),
+                Gate::new(6, GateType::And, vec![5, 1]),
+                Gate::new(7, GateType::And, vec![1, 0]),
+                Gate::new(8, GateType::And, vec![4, 2]),
+                Gate::new(9, GateType::Xor, vec![6, 7]),
+                Gate::new(10, GateType::Or, vec![8, 9]),
+            ],
+            vec![10, 4],
+        );
+        let optimized = original
+            .optimize_with(&[
+                Pass::CollapseNots,
+                Pass::MergeDuplicates,
+                Pass::RemoveDeadGates,
+            ])
+            .unwrap();
+        assert_equivalent(&original, &optimized);
+
+        // The double negation goes and the other NOT is absorbed by the AND,
+        // whose duplicate goes too. The NOT stays for the output.
+        let types: Vec<(u32, GateType)> = optimized
+            .gates
+            .iter()
+            .map(|gate| (gate.id, gate.gate_type))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (4, GateType::Not),
+                (6, GateType::And),
+                (8, GateType::CNImply),
+                (9, GateType::Xor),
+                (10, GateType::Or)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_dead_gates() {
+        let original = circuit(
+            vec![
+                Gate::new(4, GateType::And, vec![0, 1]),
+                Gate::new(5, GateType::Or, vec![4, 2]),
+                Gate::new(6, GateType::Xor, vec![0, 2]),
+            ],
+            vec![4],
+        );
+        let optimized = original.optimize_with(&[Pass::RemoveDeadGates]).unwrap();
+        assert_equivalent(&original, &optimized);
+        assert_eq!(optimized.gates.len(), 1);
+        assert!(optimized.constants.is_empty());
+    }
+
+    #[test]
+    fn test_rewrite_ands() {
+        // A textbook ripple-carry adder spends three AND-like gates per full
+        // adder, where the majority of the carry needs one
+        let builder = CircuitBuilder::new();
+        let a: Vec<Bit> = (0..4).map(|_| builder.alice_bit()).collect();
+        let b: Vec<Bit> = (0..4).map(|_| builder.bob_bit()).collect();
+        let (sum, carry) = gadgets::ripple_add(&a, &b, Variant::Textbook);
+        for bit in sum.iter().chain([&carry]) {
+            builder.output(bit);
+        }
+        let textbook = builder.build("textbook").unwrap();
+        let optimized = textbook.optimize().unwrap();
+        assert_equivalent(&textbook, &optimized);
+        assert_eq!((table_count(&textbook), table_count(&optimized)), (10, 4));
+
+        // Gates whose function of their cone is affine need no table
+        let original = circuit(
+            vec![
+                Gate::new(4, GateType::Or, vec![0, 1]),
+                Gate::new(5, GateType::And, vec![0, 1]),
+                Gate::new(6, GateType::NImply, vec![4, 5]),
+            ],
+            vec![6],
+        );
+        let optimized = original
+            .optimize_with(&[Pass::RewriteAnds, Pass::RemoveDeadGates])
+            .unwrap();
+        assert_equivalent(&original, &optimized);
+        assert_eq!(table_count(&optimized), 0);
+    }
+
+    #[test]
+    fn test_optimize_preserves_functions() {
+        for original in round_trip_circuits() {
+            let optimized = original.optimize().unwrap();
+            assert_equivalent(&original, &optimized);
+            assert!(table_count(&optimized) <= table_count(&original));
+            assert_eq!(optimized.out, original.out);
+        }
+    }
+}
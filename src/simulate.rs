@@ -0,0 +1,388 @@
+use crate::circuit::{Circuit, GateType};
+use crate::error::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+/// Word of bit-sliced wire values, holding one input vector per bit
+pub trait Lanes:
+    Copy
+    + PartialEq
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+{
+    /// Vectors per word
+    const LANES: usize;
+    const ZERO: Self;
+    const ONES: Self;
+
+    /// Bit of one lane
+    fn lane(self, lane: usize) -> u8;
+
+    /// Word whose lanes hold the bits `bit` returns, nonzero counting as 1
+    fn from_lanes(bit: impl Fn(usize) -> u8) -> Self;
+
+    /// Lowest lane whose bit is set, if any
+    fn lowest_lane(self) -> Option<usize>;
+
+    fn random(rng: &mut StdRng) -> Self;
+}
+
+macro_rules! lanes {
+    ($word:ty) => {
+        impl Lanes for $word {
+            const LANES: usize = <$word>::BITS as usize;
+            const ZERO: Self = 0;
+            const ONES: Self = <$word>::MAX;
+
+            fn lane(self, lane: usize) -> u8 {
+                ((self >> lane) & 1) as u8
+            }
+
+            fn from_lanes(bit: impl Fn(usize) -> u8) -> Self {
+                (0..Self::LANES).fold(0, |word, lane| {
+                    word | (<$word>::from(bit(lane) != 0) << lane)
+                })
+            }
+
+            fn lowest_lane(self) -> Option<usize> {
+                (self != 0).then(|| self.trailing_zeros() as usize)
+            }
+
+            fn random(rng: &mut StdRng) -> Self {
+                rng.gen()
+            }
+        }
+    };
+}
+
+lanes!(u64);
+lanes!(u128);
+
+/// Word with every lane set to `bit`
+fn splat<L: Lanes>(bit: bool) -> L {
+    if bit {
+        L::ONES
+    } else {
+        L::ZERO
+    }
+}
+
+/// Gate compiled to word operations over value slots
+#[derive(Clone, Debug)]
+enum Op {
+    Constant(bool),
+    /// XOR of the slots, negated when the flag is set
+    Affine(Vec<usize>, bool),
+    /// `((a ^ alpha) & (b ^ beta)) ^ gamma` over slots `a` and `b`
+    And([usize; 2], [bool; 3]),
+    /// Table over the slots, the first being the most significant bit of the
+    /// row index
+    Table(Vec<usize>, Vec<u8>),
+}
+
+impl Op {
+    fn eval<L: Lanes>(&self, values: &[L]) -> L {
+        match self {
+            Op::Constant(bit) => splat(*bit),
+            Op::Affine(slots, negated) => slots
+                .iter()
+                .fold(splat(*negated), |word, &slot| word ^ values[slot]),
+            Op::And([a, b], [alpha, beta, gamma]) => {
+                ((values[*a] ^ splat(*alpha)) & (values[*b] ^ splat(*beta))) ^ splat(*gamma)
+            }
+            Op::Table(slots, table) => {
+                // Select between row pairs on each input, the last first
+                let mut rows: Vec<L> = table.iter().map(|&bit| splat(bit & 1 == 1)).collect();
+                for &slot in slots.iter().rev() {
+                    let word = values[slot];
+                    rows = rows
+                        .chunks(2)
+                        .map(|pair| (word & pair[1]) | (!word & pair[0]))
+                        .collect();
+                }
+                rows[0]
+            }
+        }
+    }
+}
+
+/// Input vectors to simulate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vectors {
+    /// Every assignment of the inputs, in counting order with the first
+    /// input as the least significant bit
+    Exhaustive,
+    /// `count` assignments drawn from a generator seeded with `seed`
+    Random { count: u64, seed: u64 },
+}
+
+/// Input and output words of one batch of simulated vectors
+#[derive(Clone, Debug)]
+pub struct Batch<L> {
+    /// One word per input, in the order of `Circuit::input_wires`
+    pub inputs: Vec<L>,
+    /// One word per output, in the order of `Circuit::out`
+    pub outputs: Vec<L>,
+    /// Lanes holding vectors, the lowest ones
+    pub lanes: usize,
+}
+
+impl<L: Lanes> Batch<L> {
+    /// Input bits of the vector in one lane
+    pub fn input_bits(&self, lane: usize) -> Vec<u8> {
+        self.inputs.iter().map(|word| word.lane(lane)).collect()
+    }
+
+    /// Output bits of the vector in one lane
+    pub fn output_bits(&self, lane: usize) -> Vec<u8> {
+        self.outputs.iter().map(|word| word.lane(lane)).collect()
+    }
+
+    /// Lowest lane holding a vector whose outputs differ from `outputs`
+    pub fn first_difference(&self, outputs: &[L]) -> Option<usize> {
+        let used = L::from_lanes(|lane| u8::from(lane < self.lanes));
+        self.outputs
+            .iter()
+            .zip(outputs)
+            .filter_map(|(&word, &other)| ((word ^ other) & used).lowest_lane())
+            .min()
+    }
+}
+
+/// Circuit compiled for bit-sliced simulation, evaluating one input vector
+/// per bit of a `u64` or `u128` word
+///
+/// Gates follow the semantics of `Circuit::evaluate_plain`.
+#[derive(Clone, Debug)]
+pub struct Simulator {
+    inputs: usize,
+    /// Gates and constants in topological order, with the slot they fill
+    ops: Vec<(usize, Op)>,
+    slots: usize,
+    outputs: Vec<usize>,
+}
+
+impl Simulator {
+    /// Compile a circuit, failing if it is not well formed
+    pub fn new(circuit: &Circuit) -> Result<Simulator> {
+        let gates = circuit.topological_order()?;
+        let mut slots: HashMap<u32, usize> = HashMap::new();
+        for wire in circuit.input_wires() {
+            slots.insert(wire, slots.len());
+        }
+        let inputs = slots.len();
+
+        let mut ops = Vec::new();
+        let mut constants: Vec<(&u32, &u8)> = circuit.constants.iter().collect();
+        constants.sort();
+        for (&wire, &value) in constants {
+            ops.push((slots.len(), Op::Constant(value != 0)));
+            slots.insert(wire, slots.len());
+        }
+        for gate in gates {
+            let inputs: Vec<usize> = gate.inputs.iter().map(|wire| slots[wire]).collect();
+            let op = match (gate.gate_type, gate.gate_type.affine_form()) {
+                (GateType::Lut, _) => Op::Table(inputs, gate.truth_table()),
+                (_, Some((coefficients, constant))) => Op::Affine(
+                    inputs
+                        .iter()
+                        .zip(&coefficients)
+                        .filter(|(_, &coefficient)| coefficient == 1)
+                        .map(|(&slot, _)| slot)
+                        .collect(),
+                    constant == 1,
+                ),
+                _ => match gate.gate_type.and_form() {
+                    Some((alpha, beta, gamma)) => {
+                        Op::And([inputs[0], inputs[1]], [alpha == 1, beta == 1, gamma == 1])
+                    }
+                    None => Op::Table(inputs, gate.truth_table()),
+                },
+            };
+            ops.push((slots.len(), op));
+            slots.insert(gate.id, slots.len());
+        }
+
+        Ok(Simulator {
+            inputs,
+            ops,
+            slots: slots.len(),
+            outputs: circuit.out.iter().map(|wire| slots[wire]).collect(),
+        })
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Output words for input words, one word per input in the order of
+    /// `Circuit::input_wires`
+    ///
+    /// Panics if the number of input words is wrong.
+    pub fn run<L: Lanes>(&self, inputs: &[L]) -> Vec<L> {
+        let mut values = Vec::new();
+        self.run_into(inputs, &mut values)
+    }
+
+    /// `run` reusing the value buffer `values`
+    fn run_into<L: Lanes>(&self, inputs: &[L], values: &mut Vec<L>) -> Vec<L> {
+        assert_eq!(inputs.len(), self.inputs, "expected one word per input");
+        values.clear();
+        values.extend_from_slice(inputs);
+        values.resize(self.slots, L::ZERO);
+        for (slot, op) in &self.ops {
+            values[*slot] = op.eval(values);
+        }
+        self.outputs.iter().map(|&slot| values[slot]).collect()
+    }
+
+    /// Simulate the vectors in batches of one word, calling `visit` with
+    /// every batch until it returns `false`
+    ///
+    /// Panics if exhaustive simulation is asked of 64 or more inputs.
+    pub fn simulate<L: Lanes>(&self, vectors: Vectors, mut visit: impl FnMut(&Batch<L>) -> bool) {
+        let lane_bits = L::LANES.trailing_zeros() as usize;
+        let (batches, total) = match vectors {
+            Vectors::Exhaustive => {
+                assert!(self.inputs < 64, "too many inputs to enumerate");
+                let total = 1u64 << self.inputs;
+                (total.div_ceil(L::LANES as u64), total)
+            }
+            Vectors::Random { count, .. } => (count.div_ceil(L::LANES as u64), count),
+        };
+        let mut rng = match vectors {
+            Vectors::Random { seed, .. } => StdRng::seed_from_u64(seed),
+            Vectors::Exhaustive => StdRng::seed_from_u64(0),
+        };
+
+        // Inputs below `lane_bits` count within a word, the others by batch
+        let patterns: Vec<L> = (0..lane_bits)
+            .map(|input| L::from_lanes(|lane| ((lane >> input) & 1) as u8))
+            .collect();
+        let mut values = Vec::new();
+        for batch in 0..batches {
+            let inputs: Vec<L> = match vectors {
+                Vectors::Exhaustive => (0..self.inputs)
+                    .map(|input| match patterns.get(input) {
+                        Some(&pattern) => pattern,
+                        None => splat((batch >> (input - lane_bits)) & 1 == 1),
+                    })
+                    .collect(),
+                Vectors::Random { .. } => (0..self.inputs).map(|_| L::random(&mut rng)).collect(),
+            };
+            let outputs = self.run_into(&inputs, &mut values);
+            let lanes = (total - batch * L::LANES as u64).min(L::LANES as u64) as usize;
+            if !visit(&Batch {
+                inputs,
+                outputs,
+                lanes,
+            }) {
+                return;
+            }
+        }
+    }
+
+    /// First input vector, in the order of `Circuit::input_wires`, whose
+    /// outputs differ from the ones `expected` gives for it
+    pub fn find_mismatch(
+        &self,
+        vectors: Vectors,
+        mut expected: impl FnMut(&[u8]) -> Vec<u8>,
+    ) -> Option<Vec<u8>> {
+        let mut mismatch = None;
+        self.simulate::<u64>(vectors, |batch| {
+            mismatch = (0..batch.lanes).find_map(|lane| {
+                let inputs = batch.input_bits(lane);
+                (expected(&inputs) != batch.output_bits(lane)).then_some(inputs)
+            });
+            mismatch.is_none()
+        });
+        mismatch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::CircuitBuilder;
+    use crate::test_support::{evaluate, round_trip_circuits};
+
+    #[test]
+    fn test_simulator_matches_evaluate_plain() {
+        for circuit in round_trip_circuits() {
+            let simulator = Simulator::new(&circuit).unwrap();
+            let mismatch =
+                simulator.find_mismatch(Vectors::Exhaustive, |inputs| evaluate(&circuit, inputs));
+            assert_eq!(mismatch, None, "{}", circuit.id);
+
+            // Wide words and random vectors give the same outputs
+            let mut count = 0;
+            simulator.simulate::<u128>(
+                Vectors::Random {
+                    count: 300,
+                    seed: 7,
+                },
+                |batch| {
+                    for lane in 0..batch.lanes {
+                        let expected = evaluate(&circuit, &batch.input_bits(lane));
+                        assert_eq!(batch.output_bits(lane), expected, "{}", circuit.id);
+                    }
+                    count += batch.lanes;
+                    true
+                },
+            );
+            assert_eq!(count, 300);
+        }
+    }
+
+    #[test]
+    fn test_exhaustive_vectors() {
+        // Eight inputs spread over four 64-lane words, in counting order
+        let builder = CircuitBuilder::new();
+        let a = builder.alice_uint::<4>();
+        let b = builder.bob_uint::<4>();
+        builder.output_uint(&(&a + &b));
+        let circuit = builder.build("add").unwrap();
+        let simulator = Simulator::new(&circuit).unwrap();
+
+        let mut next = 0u32;
+        simulator.simulate::<u64>(Vectors::Exhaustive, |batch| {
+            assert_eq!(batch.lanes, 64);
+            for lane in 0..batch.lanes {
+                let bits = batch.input_bits(lane);
+                let vector = bits
+                    .iter()
+                    .rev()
+                    .fold(0, |value, &bit| value << 1 | bit as u32);
+                assert_eq!(vector, next);
+                next += 1;
+            }
+            true
+        });
+        assert_eq!(next, 256);
+
+        let add = |inputs: &[u8]| {
+            let value = |bits: &[u8]| bits.iter().rev().fold(0, |value, &bit| value << 1 | bit);
+            let sum = (value(&inputs[..4]) + value(&inputs[4..])) & 15;
+            (0..4).map(|i| (sum >> i) & 1).collect()
+        };
+        assert_eq!(simulator.find_mismatch(Vectors::Exhaustive, add), None);
+
+        // A wrong expectation is caught with the first vector it fails on
+        let wrong = |inputs: &[u8]| {
+            let mut outputs = add(inputs);
+            outputs[3] &= u8::from(inputs[0] == 0 || inputs[4] == 0);
+            outputs
+        };
+        let mismatch = simulator.find_mismatch(Vectors::Exhaustive, wrong);
+        assert_eq!(mismatch, Some(vec![1, 1, 1, 0, 1, 0, 0, 0]));
+    }
+}
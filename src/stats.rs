@@ -0,0 +1,317 @@
+use crate::circuit::{Circuit, Gate, GateType};
+use crate::error::Result;
+use crate::garbled::GarblingScheme;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Bytes of a wire label
+const LABEL_BYTES: usize = 16;
+
+/// Bytes of a half-size three-halves ciphertext
+const HALF_LABEL_BYTES: usize = 8;
+
+/// Bytes of the control bits of a three-halves table
+const CONTROL_BYTES: usize = 2;
+
+/// Estimated cost of running a circuit under one garbling scheme
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemeCost {
+    pub scheme: GarblingScheme,
+    /// Gates garbled with a table
+    pub tables: usize,
+    /// Bytes of all garbled tables
+    pub table_bytes: usize,
+    /// Bytes of the garbled circuit sent to the evaluator: the tables, the
+    /// labels of constants and the decoding hashes of the outputs
+    pub garbled_bytes: usize,
+    /// Bytes of the labels of Alice's inputs, sent alongside
+    pub alice_label_bytes: usize,
+    /// Oblivious transfers, one per input of Bob
+    pub oblivious_transfers: usize,
+    /// Bytes of the label pairs offered through oblivious transfer, before
+    /// the overhead of the transfer itself
+    pub ot_label_bytes: usize,
+}
+
+/// Size, depth and cost figures of a circuit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitStats {
+    pub id: String,
+    /// Gates of every type present, in the order of `GateType::ALL`
+    pub gate_counts: Vec<(GateType, usize)>,
+    pub gates: usize,
+    /// Gates with an AND-like function, which need a table under every scheme
+    pub and_gates: usize,
+    /// Most gates on a path from an input to an output
+    pub depth: usize,
+    /// Most gates needing a table under Free XOR on a path from an input to
+    /// an output
+    pub multiplicative_depth: usize,
+    /// Number of wires for every fan-out, counting uses as a gate input or as
+    /// an output
+    pub fan_out: BTreeMap<usize, usize>,
+    pub alice_inputs: usize,
+    pub bob_inputs: usize,
+    pub constants: usize,
+    pub outputs: usize,
+    /// Distinct wires: party inputs, constants and gate outputs
+    pub wires: usize,
+    /// Costs under every scheme of `GarblingScheme::ALL`
+    pub costs: Vec<SchemeCost>,
+}
+
+/// Bytes of the garbled table of a gate under a scheme
+fn table_bytes(scheme: GarblingScheme, gate: &Gate) -> usize {
+    let rows = 1 << gate.inputs.len();
+    let and_like = gate.gate_type.and_form().is_some();
+    match scheme {
+        _ if scheme.is_free(gate.gate_type) => 0,
+        GarblingScheme::Grr3 => (rows - 1) * LABEL_BYTES,
+        GarblingScheme::HalfGates if and_like => 2 * LABEL_BYTES,
+        GarblingScheme::ThreeHalves if and_like => 3 * HALF_LABEL_BYTES + CONTROL_BYTES,
+        _ => rows * LABEL_BYTES,
+    }
+}
+
+impl SchemeCost {
+    fn new(circuit: &Circuit, scheme: GarblingScheme) -> Self {
+        let garbled: Vec<usize> = circuit
+            .gates
+            .iter()
+            .filter(|gate| !scheme.is_free(gate.gate_type))
+            .map(|gate| table_bytes(scheme, gate))
+            .collect();
+        let table_bytes: usize = garbled.iter().sum();
+
+        // Constant wires and ZERO/ONE gates have a public label each, and
+        // every distinct output has the hashes of both its labels
+        let constant_labels = circuit.constants.len()
+            + circuit
+                .gates
+                .iter()
+                .filter(|gate| gate.gate_type.arity() == Some(0))
+                .count();
+        let mut outputs = circuit.out.clone();
+        outputs.sort_unstable();
+        outputs.dedup();
+        let bob_inputs = circuit.bob.iter().flatten().count();
+        SchemeCost {
+            scheme,
+            tables: garbled.len(),
+            table_bytes,
+            garbled_bytes: table_bytes + (constant_labels + 2 * outputs.len()) * LABEL_BYTES,
+            alice_label_bytes: circuit.alice.iter().flatten().count() * LABEL_BYTES,
+            oblivious_transfers: bob_inputs,
+            ot_label_bytes: 2 * bob_inputs * LABEL_BYTES,
+        }
+    }
+}
+
+impl Circuit {
+    /// Gate counts, depths, fan-out and estimated garbling costs
+    ///
+    /// Fails if the circuit is not well formed.
+    pub fn stats(&self) -> Result<CircuitStats> {
+        // Depth and multiplicative depth of every wire, inputs and constants
+        // being at depth 0 like ZERO/ONE gates
+        let mut depths: HashMap<u32, (usize, usize)> = self
+            .input_wires()
+            .chain(self.constants.keys().copied())
+            .map(|wire| (wire, (0, 0)))
+            .collect();
+        for gate in self.topological_order()? {
+            let (depth, multiplicative) = gate
+                .inputs
+                .iter()
+                .map(|wire| depths[wire])
+                .fold((0, 0), |(a, b), (c, d)| (a.max(c), b.max(d)));
+            let (step, product) = match gate.gate_type.arity() {
+                Some(0) => (0, 0),
+                _ => (
+                    1,
+                    usize::from(!GarblingScheme::FreeXor.is_free(gate.gate_type)),
+                ),
+            };
+            depths.insert(gate.id, (depth + step, multiplicative + product));
+        }
+        let (depth, multiplicative_depth) = self
+            .out
+            .iter()
+            .map(|wire| depths[wire])
+            .fold((0, 0), |(a, b), (c, d)| (a.max(c), b.max(d)));
+
+        let mut uses: HashMap<u32, usize> = depths.keys().map(|&wire| (wire, 0)).collect();
+        for &wire in self
+            .gates
+            .iter()
+            .flat_map(|gate| &gate.inputs)
+            .chain(&self.out)
+        {
+            *uses.entry(wire).or_default() += 1;
+        }
+        let mut fan_out = BTreeMap::new();
+        for count in uses.into_values() {
+            *fan_out.entry(count).or_default() += 1;
+        }
+
+        let gate_counts = GateType::ALL
+            .into_iter()
+            .map(|gate_type| {
+                let count = self
+                    .gates
+                    .iter()
+                    .filter(|gate| gate.gate_type == gate_type)
+                    .count();
+                (gate_type, count)
+            })
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        Ok(CircuitStats {
+            id: self.id.clone(),
+            gate_counts,
+            gates: self.gates.len(),
+            and_gates: self
+                .gates
+                .iter()
+                .filter(|gate| gate.gate_type.and_form().is_some())
+                .count(),
+            depth,
+            multiplicative_depth,
+            fan_out,
+            alice_inputs: self.alice.iter().flatten().count(),
+            bob_inputs: self.bob.iter().flatten().count(),
+            constants: self.constants.len(),
+            outputs: self.out.len(),
+            wires: depths.len(),
+            costs: GarblingScheme::ALL
+                .into_iter()
+                .map(|scheme| SchemeCost::new(self, scheme))
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Circuit {}", self.id)?;
+        writeln!(
+            f,
+            "Wires: {} ({} Alice inputs, {} Bob inputs, {} constants, {} outputs)",
+            self.wires, self.alice_inputs, self.bob_inputs, self.constants, self.outputs
+        )?;
+        writeln!(f, "Gates: {} ({} AND-like)", self.gates, self.and_gates)?;
+        for (gate_type, count) in &self.gate_counts {
+            let name = format!("{gate_type:?}").to_uppercase();
+            writeln!(f, "  {name:<8} {count}")?;
+        }
+        writeln!(
+            f,
+            "Depth: {}, multiplicative depth: {}",
+            self.depth, self.multiplicative_depth
+        )?;
+        let fan_out: Vec<String> = self
+            .fan_out
+            .iter()
+            .map(|(uses, wires)| format!("{uses}: {wires}"))
+            .collect();
+        writeln!(f, "Fan-out (uses: wires): {}", fan_out.join(", "))?;
+        writeln!(
+            f,
+            "{:<12} {:>8} {:>12} {:>14} {:>12} {:>6} {:>10}",
+            "Scheme", "Tables", "Table bytes", "Garbled bytes", "Alice bytes", "OTs", "OT bytes"
+        )?;
+        for cost in &self.costs {
+            writeln!(
+                f,
+                "{:<12} {:>8} {:>12} {:>14} {:>12} {:>6} {:>10}",
+                format!("{:?}", cost.scheme),
+                cost.tables,
+                cost.table_bytes,
+                cost.garbled_bytes,
+                cost.alice_label_bytes,
+                cost.oblivious_transfers,
+                cost.ot_label_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::garbled::Garbler;
+
+    fn circuit() -> Circuit {
+        Circuit {
+            id: "stats".to_string(),
+            gates: vec![
+                Gate::new(4, GateType::And, vec![0, 2]),
+                Gate::new(5, GateType::Xor, vec![4, 1]),
+                Gate::new(6, GateType::Or, vec![5, 3]),
+                Gate::new(7, GateType::Not, vec![6]),
+                Gate::lut(8, vec![0, 1, 2], vec![0, 1, 1, 0, 1, 0, 0, 1]),
+                Gate::new(9, GateType::One, vec![]),
+            ],
+            alice: Some(vec![0, 1]),
+            bob: Some(vec![2]),
+            out: vec![7, 8, 4, 9],
+            constants: HashMap::from([(3, 0)]),
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = circuit().stats().unwrap();
+        assert_eq!(
+            stats.gate_counts,
+            vec![
+                (GateType::One, 1),
+                (GateType::And, 1),
+                (GateType::Or, 1),
+                (GateType::Xor, 1),
+                (GateType::Not, 1),
+                (GateType::Lut, 1)
+            ]
+        );
+        assert_eq!((stats.gates, stats.and_gates), (6, 2));
+        assert_eq!((stats.depth, stats.multiplicative_depth), (4, 2));
+        assert_eq!(stats.fan_out, BTreeMap::from([(1, 6), (2, 4)]));
+        assert_eq!((stats.alice_inputs, stats.bob_inputs), (2, 1));
+        assert_eq!((stats.constants, stats.outputs, stats.wires), (1, 4, 10));
+
+        let free_xor = &stats.costs[2];
+        assert_eq!(free_xor.scheme, GarblingScheme::FreeXor);
+        assert_eq!((free_xor.tables, free_xor.table_bytes), (3, 16 * 16));
+        assert_eq!(free_xor.garbled_bytes, (16 + 2 + 8) * 16);
+        assert_eq!(
+            (free_xor.oblivious_transfers, free_xor.ot_label_bytes),
+            (1, 32)
+        );
+        assert!(stats.to_string().contains("Gates: 6 (2 AND-like)"));
+    }
+
+    #[test]
+    fn test_table_bytes_match_garbler() {
+        for scheme in GarblingScheme::ALL {
+            let circuit = circuit();
+            let cost = SchemeCost::new(&circuit, scheme);
+            let garbler = Garbler::with_scheme(circuit, scheme).unwrap();
+            let garbled = garbler.garbled_circuit();
+            let tables: Vec<usize> = garbled
+                .garbled_gates
+                .iter()
+                .map(|gate| gate.garbled_table.iter().map(Vec::len).sum())
+                .filter(|&bytes| bytes > 0)
+                .collect();
+            assert_eq!(tables.len(), cost.tables, "{scheme:?}");
+            assert_eq!(tables.iter().sum::<usize>(), cost.table_bytes, "{scheme:?}");
+            let labels = garbled.constant_keys.len() + 2 * garbled.output_decoding.len();
+            assert_eq!(
+                cost.table_bytes + labels * LABEL_BYTES,
+                cost.garbled_bytes,
+                "{scheme:?}"
+            );
+        }
+    }
+}
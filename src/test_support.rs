@@ -0,0 +1,72 @@
+use crate::circuit::{Circuit, Gate, GateType};
+use std::collections::HashMap;
+
+/// Output bits of a circuit in the clear, for the given input bits in
+/// the order of `input_wires`
+pub fn evaluate(circuit: &Circuit, input_bits: &[u8]) -> Vec<u8> {
+    let inputs = circuit
+        .input_wires()
+        .zip(input_bits.iter().copied())
+        .collect();
+    let outputs = circuit.evaluate_plain(&inputs).unwrap();
+    circuit.out.iter().map(|wire| outputs[wire]).collect()
+}
+
+/// Check both circuits agree on every input assignment
+pub fn assert_equivalent(left: &Circuit, right: &Circuit) {
+    let input_count = left.input_wires().count();
+    assert_eq!(input_count, right.input_wires().count());
+    for assignment in 0..1u32 << input_count {
+        let bits: Vec<u8> = (0..input_count)
+            .map(|i| ((assignment >> i) & 1) as u8)
+            .collect();
+        assert_eq!(
+            evaluate(left, &bits),
+            evaluate(right, &bits),
+            "{} on {bits:?}",
+            left.id
+        );
+    }
+}
+
+/// The bundled circuits plus one with every gate type, a LUT, a constant
+/// and outputs that are inputs or repeated
+pub fn round_trip_circuits() -> Vec<Circuit> {
+    let mut circuits = Circuit::from_json_file("circuits/bool.json").unwrap();
+    circuits.extend(Circuit::from_json_file("circuits/max.json").unwrap());
+
+    let mut gates: Vec<Gate> = [
+        GateType::Nand,
+        GateType::Nor,
+        GateType::Xnor,
+        GateType::NImply,
+        GateType::CNImply,
+        GateType::Imply,
+        GateType::CImply,
+        GateType::ProjA,
+        GateType::ProjB,
+        GateType::NotA,
+        GateType::NotB,
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, gate_type)| Gate::new(10 + i as u32, gate_type, vec![1, 2]))
+    .collect();
+    gates.push(Gate::new(30, GateType::Buf, vec![3]));
+    gates.push(Gate::new(31, GateType::Zero, vec![]));
+    gates.push(Gate::lut(32, vec![1, 2, 4], vec![0, 1, 1, 0, 1, 0, 0, 1]));
+    gates.push(Gate::lut(33, vec![1, 2, 3], vec![1, 1, 1, 0, 1, 1, 1, 1]));
+    let mut out: Vec<u32> = gates.iter().map(|gate| gate.id).collect();
+    out.extend([1, 33, 4]);
+    let mut circuit = Circuit {
+        id: "all gates".to_string(),
+        gates,
+        alice: Some(vec![1, 2]),
+        bob: Some(vec![3]),
+        out,
+        constants: HashMap::new(),
+    };
+    circuit.constants.insert(4, 1);
+    circuits.push(circuit);
+    circuits
+}
@@ -1,7 +1,7 @@
 use crate::circuit::Circuit;
-use crate::garbled::GarbledCircuit;
+use crate::error::{Error, Result};
+use crate::garbled::{GarbledCircuit, Garbler};
 use crate::key::Key;
-use anyhow::{Context, Result};
 use oblivious_transfer_rs::{
     Choice, OTReceiver, OTSender, ReceiverEncryptedValues, SenderMaskedMessages, SenderPublicKey,
 };
@@ -13,7 +13,7 @@ const AES_KEY_SIZE: usize = 16;
 /// Alice's side of the garbled circuit protocol
 pub struct Alice {
     pub circuit: Circuit,
-    pub garbled_circuit: GarbledCircuit,
+    pub garbler: Garbler,
     pub inputs: HashMap<u32, u8>,
 }
 
@@ -24,19 +24,21 @@ pub struct Bob {
 }
 
 impl Alice {
-    pub fn new(circuit: Circuit, inputs: HashMap<u32, u8>) -> Self {
-        let garbled_circuit = GarbledCircuit::new(circuit.clone());
-        Alice {
+    pub fn new(circuit: Circuit, inputs: HashMap<u32, u8>) -> Result<Self> {
+        let garbler = Garbler::new(circuit.clone())?;
+        Ok(Alice {
             circuit,
-            garbled_circuit,
+            garbler,
             inputs,
-        }
+        })
     }
 
     /// Alice prepares the garbled circuit and sends it to Bob
+    ///
+    /// Only the evaluator view leaves Alice, her wire labels stay in the garbler.
     pub fn send_garbled_circuit(&self) -> GarbledCircuit {
         // In a real implementation, this would be sent over the network
-        self.garbled_circuit.clone()
+        self.garbler.garbled_circuit().clone()
     }
 
     /// Alice sets up OT senders for Bob's input wires
@@ -47,12 +49,13 @@ impl Alice {
             .circuit
             .bob
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Circuit must have Bob inputs for OT protocol"))?;
+            .ok_or_else(|| Error::Ot("circuit must have Bob inputs".to_string()))?;
 
         for &wire_id in bob_wires {
-            if let Some((key0, key1)) = self.garbled_circuit.get_all_keys().get(&wire_id) {
+            if let Some((key0, key1)) = self.garbler.input_keys(wire_id) {
                 // Set up OT sender for Bob's wire with both possible keys
-                let sender = OTSender::new(key0.0.to_vec(), key1.0.to_vec())?;
+                let sender = OTSender::new(key0.0.to_vec(), key1.0.to_vec())
+                    .map_err(|e| Error::Ot(format!("sender setup for wire {wire_id}: {e}")))?;
                 ot_senders.insert(wire_id, sender);
             }
         }
@@ -68,7 +71,9 @@ impl Alice {
         let mut sender_public_keys = HashMap::new();
 
         for (wire_id, sender) in ot_senders.iter_mut() {
-            let public_key = sender.generate_keys()?;
+            let public_key = sender
+                .generate_keys()
+                .map_err(|e| Error::Ot(format!("key generation for wire {wire_id}: {e}")))?;
             sender_public_keys.insert(*wire_id, public_key);
         }
 
@@ -84,30 +89,31 @@ impl Alice {
     ) -> Result<SenderMaskedMessages> {
         let sender = ot_senders
             .get(&wire_id)
-            .with_context(|| format!("No OT sender found for wire {wire_id}"))?;
+            .ok_or_else(|| Error::Ot(format!("no OT sender for wire {wire_id}")))?;
         sender
             .create_masked_messages(encrypted_values)
-            .with_context(|| format!("Failed to create masked messages for wire {wire_id}"))
+            .map_err(|e| Error::Ot(format!("masking messages for wire {wire_id}: {e}")))
     }
 
     /// Alice sends her input keys directly to Bob
     /// For Alice's wires, Bob just receives the keys corresponding to Alice's actual inputs
-    pub fn send_alice_input_keys(&self) -> HashMap<u32, Key> {
+    pub fn send_alice_input_keys(&self) -> Result<HashMap<u32, Key>> {
         let mut alice_keys = HashMap::new();
 
         if let Some(ref alice_wires) = self.circuit.alice {
             for &wire_id in alice_wires {
-                if let Some(&alice_bit) = self.inputs.get(&wire_id) {
-                    if let Some((key0, key1)) = self.garbled_circuit.get_all_keys().get(&wire_id) {
-                        // Alice sends the key corresponding to her actual input
-                        let selected_key = if alice_bit == 0 { key0 } else { key1 };
-                        alice_keys.insert(wire_id, selected_key.clone());
-                    }
+                let alice_bit = *self
+                    .inputs
+                    .get(&wire_id)
+                    .ok_or(Error::MissingInput(wire_id))?;
+                // Alice sends the key corresponding to her actual input
+                if let Some(selected_key) = self.garbler.encode_input(wire_id, alice_bit) {
+                    alice_keys.insert(wire_id, selected_key.clone());
                 }
             }
         }
 
-        alice_keys
+        Ok(alice_keys)
     }
 }
 
@@ -133,21 +139,25 @@ impl Bob {
         let mut result = Vec::new();
 
         for &wire_id in bob_wires {
-            if let Some(&bob_bit) = self.inputs.get(&wire_id) {
-                // Bob chooses based on his own input
-                let choice = if bob_bit == 0 {
-                    Choice::Zero
-                } else {
-                    Choice::One
-                };
-
-                // Phase 2: Bob generates encrypted values based on his choice
-                let mut receiver = OTReceiver::new(choice);
-
-                if let Some(sender_pk) = sender_public_keys.get(&wire_id) {
-                    let encrypted_values = receiver.generate_encrypted_values(sender_pk.clone())?;
-                    result.push((wire_id, encrypted_values, receiver));
-                }
+            let bob_bit = *self
+                .inputs
+                .get(&wire_id)
+                .ok_or(Error::MissingInput(wire_id))?;
+            // Bob chooses based on his own input
+            let choice = if bob_bit == 0 {
+                Choice::Zero
+            } else {
+                Choice::One
+            };
+
+            // Phase 2: Bob generates encrypted values based on his choice
+            let mut receiver = OTReceiver::new(choice);
+
+            if let Some(sender_pk) = sender_public_keys.get(&wire_id) {
+                let encrypted_values = receiver
+                    .generate_encrypted_values(sender_pk.clone())
+                    .map_err(|e| Error::Ot(format!("encrypting choice for wire {wire_id}: {e}")))?;
+                result.push((wire_id, encrypted_values, receiver));
             }
         }
 
@@ -161,7 +171,9 @@ impl Bob {
     ) -> Result<()> {
         for (wire_id, masked_msgs, receiver) in masked_messages {
             // Phase 4: Bob extracts only the chosen message
-            let decrypted_key_bytes = receiver.extract_message(masked_msgs)?;
+            let decrypted_key_bytes = receiver
+                .extract_message(masked_msgs)
+                .map_err(|e| Error::Ot(format!("extracting message for wire {wire_id}: {e}")))?;
 
             // Convert to Key and store
             if decrypted_key_bytes.len() == AES_KEY_SIZE {
@@ -169,21 +181,21 @@ impl Bob {
                 key_array.copy_from_slice(&decrypted_key_bytes);
                 self.received_keys.insert(wire_id, Key(key_array));
             } else {
-                return Err(anyhow::anyhow!(
-                    "Invalid key size for wire {}: expected {} bytes, got {}",
+                return Err(Error::Ot(format!(
+                    "invalid key size for wire {}: expected {} bytes, got {}",
                     wire_id,
                     AES_KEY_SIZE,
                     decrypted_key_bytes.len()
-                ));
+                )));
             }
         }
         Ok(())
     }
 
     /// Bob evaluates the garbled circuit using the keys he has
-    pub fn evaluate_circuit(&self, garbled_circuit: &GarbledCircuit) -> HashMap<u32, u8> {
+    pub fn evaluate_circuit(&self, garbled_circuit: &GarbledCircuit) -> Result<HashMap<u32, u8>> {
         // Use the shared evaluation logic from GarbledCircuit
-        let final_wire_values = garbled_circuit.evaluate_gates(self.received_keys.clone());
+        let final_wire_values = garbled_circuit.evaluate_gates(self.received_keys.clone())?;
         garbled_circuit.extract_outputs(&final_wire_values)
     }
 }
@@ -195,11 +207,11 @@ pub fn execute_secure_protocol(
     bob_inputs: HashMap<u32, u8>,
 ) -> Result<HashMap<u32, u8>> {
     // Alice's side
-    let alice = Alice::new(circuit.clone(), alice_inputs);
+    let alice = Alice::new(circuit.clone(), alice_inputs)?;
     let garbled_circuit = alice.send_garbled_circuit();
 
     // Alice sends her input keys directly to Bob (no OT needed for Alice's inputs)
-    let alice_keys = alice.send_alice_input_keys();
+    let alice_keys = alice.send_alice_input_keys()?;
 
     // Alice sets up OT senders for Bob's input wires
     let mut ot_senders = alice.setup_ot_for_bob_inputs()?;
@@ -214,7 +226,7 @@ pub fn execute_secure_protocol(
     let bob_wires = circuit
         .bob
         .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("Circuit must have Bob inputs for secure protocol"))?;
+        .ok_or_else(|| Error::Ot("circuit must have Bob inputs".to_string()))?;
 
     // Phase 1: Alice generates RSA keys
     let sender_public_keys = alice.generate_rsa_keys(&mut ot_senders)?;
@@ -233,13 +245,13 @@ pub fn execute_secure_protocol(
     bob.extract_messages(masked_messages)?;
 
     // Bob evaluates the circuit
-    Ok(bob.evaluate_circuit(&garbled_circuit))
+    bob.evaluate_circuit(&garbled_circuit)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::circuit::{Circuit, Gate};
+    use crate::circuit::{Circuit, Gate, GateType};
 
     fn create_test_and_circuit() -> Circuit {
         Circuit {
@@ -247,11 +259,8 @@ mod tests {
             alice: Some(vec![1]),
             bob: Some(vec![2]),
             out: vec![3],
-            gates: vec![Gate {
-                id: 3,
-                gate_type: "AND".to_string(),
-                inputs: vec![1, 2],
-            }],
+            constants: HashMap::new(),
+            gates: vec![Gate::new(3, GateType::And, vec![1, 2])],
         }
     }
 
@@ -261,11 +270,8 @@ mod tests {
             alice: Some(vec![1]),
             bob: Some(vec![2]),
             out: vec![3],
-            gates: vec![Gate {
-                id: 3,
-                gate_type: "OR".to_string(),
-                inputs: vec![1, 2],
-            }],
+            constants: HashMap::new(),
+            gates: vec![Gate::new(3, GateType::Or, vec![1, 2])],
         }
     }
 
@@ -290,7 +296,7 @@ mod tests {
     fn test_alice_setup() -> Result<()> {
         let circuit = create_test_and_circuit();
         let alice_inputs = [(1, 1)].iter().cloned().collect();
-        let alice = Alice::new(circuit, alice_inputs);
+        let alice = Alice::new(circuit, alice_inputs)?;
 
         // Test garbled circuit creation
         let garbled_circuit = alice.send_garbled_circuit();
@@ -298,7 +304,7 @@ mod tests {
         assert_eq!(garbled_circuit.garbled_gates.len(), 1);
 
         // Test Alice's input key sending
-        let alice_keys = alice.send_alice_input_keys();
+        let alice_keys = alice.send_alice_input_keys()?;
         assert_eq!(alice_keys.len(), 1);
         assert!(alice_keys.contains_key(&1));
 
@@ -395,11 +401,8 @@ mod tests {
             alice: Some(vec![1]),
             bob: Some(vec![2, 3]),
             out: vec![4],
-            gates: vec![Gate {
-                id: 4,
-                gate_type: "AND".to_string(),
-                inputs: vec![1, 2],
-            }],
+            constants: HashMap::new(),
+            gates: vec![Gate::new(4, GateType::And, vec![1, 2])],
         };
 
         let alice_inputs = [(1, 1)].iter().cloned().collect();
@@ -419,18 +422,15 @@ mod tests {
             alice: Some(vec![1]),
             bob: None,
             out: vec![2],
-            gates: vec![Gate {
-                id: 2,
-                gate_type: "NOT".to_string(),
-                inputs: vec![1],
-            }],
+            constants: HashMap::new(),
+            gates: vec![Gate::new(2, GateType::Not, vec![1])],
         };
 
         let alice_inputs = [(1, 0)].iter().cloned().collect();
         let bob_inputs = HashMap::new();
 
         let result = execute_secure_protocol(circuit, alice_inputs, bob_inputs);
-        assert!(result.is_err()); // Should fail because Bob inputs are required
+        assert!(matches!(result, Err(Error::Ot(_)))); // Should fail because Bob inputs are required
 
         Ok(())
     }
@@ -443,17 +443,10 @@ mod tests {
             alice: Some(vec![1, 2]),
             bob: Some(vec![3]),
             out: vec![5],
+            constants: HashMap::new(),
             gates: vec![
-                Gate {
-                    id: 4,
-                    gate_type: "AND".to_string(),
-                    inputs: vec![1, 2],
-                },
-                Gate {
-                    id: 5,
-                    gate_type: "OR".to_string(),
-                    inputs: vec![4, 3],
-                },
+                Gate::new(4, GateType::And, vec![1, 2]),
+                Gate::new(5, GateType::Or, vec![4, 3]),
             ],
         };
 
@@ -0,0 +1,462 @@
+use crate::circuit::{Circuit, Gate, GateType};
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Which party provides each named input of an imported netlist, and which
+/// named outputs become circuit outputs
+///
+/// Read from JSON such as `{"alice": ["a"], "bob": ["b"], "out": ["y"]}`.
+/// When neither party lists any input, inputs are assigned by name: those
+/// starting with `alice` to Alice and those starting with `bob` to Bob.
+/// When no output is listed, every output is used in netlist order.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct PortAssignment {
+    /// Yosys module to import, the top module when not given
+    pub module: Option<String>,
+    pub alice: Vec<String>,
+    pub bob: Vec<String>,
+    pub out: Vec<String>,
+}
+
+impl PortAssignment {
+    pub fn from_json_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Alice's and Bob's inputs among the netlist's inputs, failing if an
+    /// input belongs to neither party
+    pub(crate) fn parties<'a>(
+        &'a self,
+        inputs: &[&'a str],
+    ) -> Result<(Vec<&'a str>, Vec<&'a str>)> {
+        let (alice, bob): (Vec<&str>, Vec<&str>) = if self.alice.is_empty() && self.bob.is_empty() {
+            (
+                inputs
+                    .iter()
+                    .copied()
+                    .filter(|name| name.starts_with("alice"))
+                    .collect(),
+                inputs
+                    .iter()
+                    .copied()
+                    .filter(|name| name.starts_with("bob"))
+                    .collect(),
+            )
+        } else {
+            (
+                self.alice.iter().map(String::as_str).collect(),
+                self.bob.iter().map(String::as_str).collect(),
+            )
+        };
+        match inputs
+            .iter()
+            .find(|name| !alice.contains(name) && !bob.contains(name))
+        {
+            Some(input) => Err(Error::Import(format!(
+                "input `{input}` belongs to neither party"
+            ))),
+            None => Ok((alice, bob)),
+        }
+    }
+
+    /// Outputs to use among the netlist's outputs
+    pub(crate) fn outputs<'a>(&'a self, outputs: &[&'a str]) -> Vec<&'a str> {
+        if self.out.is_empty() {
+            outputs.to_vec()
+        } else {
+            self.out.iter().map(String::as_str).collect()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Netlist {
+    modules: BTreeMap<String, Module>,
+}
+
+#[derive(Deserialize)]
+struct Module {
+    #[serde(default)]
+    attributes: HashMap<String, Value>,
+    #[serde(default)]
+    ports: BTreeMap<String, Port>,
+    #[serde(default)]
+    cells: BTreeMap<String, Cell>,
+}
+
+impl Module {
+    /// Whether Yosys marked this module as the top of the design
+    fn is_top(&self) -> bool {
+        match self.attributes.get("top") {
+            Some(Value::String(bits)) => bits.contains('1'),
+            Some(Value::Number(number)) => number.as_u64() != Some(0),
+            _ => false,
+        }
+    }
+
+    /// Bits of a port, failing if it is missing or has another direction
+    fn port_bits(&self, name: &str, direction: &str) -> Result<&[Bit]> {
+        match self.ports.get(name) {
+            Some(port) if port.direction == direction => Ok(&port.bits),
+            _ => Err(Error::Import(format!("no {direction} port `{name}`"))),
+        }
+    }
+
+    fn port_names(&self, direction: &'static str) -> impl Iterator<Item = &str> {
+        self.ports
+            .iter()
+            .filter(move |(_, port)| port.direction == direction)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+#[derive(Deserialize)]
+struct Port {
+    direction: String,
+    bits: Vec<Bit>,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    #[serde(rename = "type")]
+    cell_type: String,
+    #[serde(default)]
+    connections: HashMap<String, Vec<Bit>>,
+}
+
+/// A net id, or one of the constants `"0"`, `"1"`, `"x"` and `"z"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Bit {
+    Net(u32),
+    Constant(String),
+}
+
+/// What a Yosys internal gate cell computes
+enum CellFunction {
+    Gate(GateType),
+    /// Cells with more than two inputs, imported as LUT gates
+    Lut(fn(&[u8]) -> u8),
+}
+
+/// Function and input ports of the Yosys internal gate cells that `synth`
+/// and `techmap` produce
+fn cell_function(cell_type: &str) -> Option<(CellFunction, &'static [&'static str])> {
+    use CellFunction::{Gate, Lut};
+    const A: &[&str] = &["A"];
+    const AB: &[&str] = &["A", "B"];
+    const ABS: &[&str] = &["A", "B", "S"];
+    const ABC: &[&str] = &["A", "B", "C"];
+    const ABCD: &[&str] = &["A", "B", "C", "D"];
+    let function = match cell_type {
+        "$_BUF_" => (Gate(GateType::Buf), A),
+        "$_NOT_" => (Gate(GateType::Not), A),
+        "$_AND_" => (Gate(GateType::And), AB),
+        "$_NAND_" => (Gate(GateType::Nand), AB),
+        "$_OR_" => (Gate(GateType::Or), AB),
+        "$_NOR_" => (Gate(GateType::Nor), AB),
+        "$_XOR_" => (Gate(GateType::Xor), AB),
+        "$_XNOR_" => (Gate(GateType::Xnor), AB),
+        "$_ANDNOT_" => (Gate(GateType::NImply), AB),
+        "$_ORNOT_" => (Gate(GateType::CImply), AB),
+        "$_MUX_" => (Lut(|v| if v[2] == 1 { v[1] } else { v[0] }), ABS),
+        "$_NMUX_" => (Lut(|v| 1 ^ if v[2] == 1 { v[1] } else { v[0] }), ABS),
+        "$_AOI3_" => (Lut(|v| 1 ^ ((v[0] & v[1]) | v[2])), ABC),
+        "$_OAI3_" => (Lut(|v| 1 ^ ((v[0] | v[1]) & v[2])), ABC),
+        "$_AOI4_" => (Lut(|v| 1 ^ ((v[0] & v[1]) | (v[2] & v[3]))), ABCD),
+        "$_OAI4_" => (Lut(|v| 1 ^ ((v[0] | v[1]) & (v[2] | v[3]))), ABCD),
+        _ => return None,
+    };
+    Some(function)
+}
+
+/// LUT table of `function`, the first input being the most significant bit
+/// of the row index
+fn lut_table(arity: usize, function: fn(&[u8]) -> u8) -> Vec<u8> {
+    (0..1usize << arity)
+        .map(|index| {
+            let bits: Vec<u8> = (0..arity)
+                .map(|j| ((index >> (arity - 1 - j)) & 1) as u8)
+                .collect();
+            function(&bits)
+        })
+        .collect()
+}
+
+/// Wire ids of netlist bits: nets keep their id and each constant value gets
+/// one constant wire past the largest net id
+struct Wires {
+    /// Next free wire id, or `None` once the ids past the nets run out
+    next: Option<u32>,
+    constants: HashMap<u32, u8>,
+}
+
+impl Wires {
+    fn wire(&mut self, bit: &Bit) -> Result<u32> {
+        let value = match bit {
+            Bit::Net(net) => return Ok(*net),
+            // An undefined bit may take any value
+            Bit::Constant(constant) if constant == "0" || constant == "x" => 0,
+            Bit::Constant(constant) if constant == "1" => 1,
+            Bit::Constant(constant) => {
+                return Err(Error::Import(format!("unsupported bit `{constant}`")))
+            }
+        };
+        if let Some((&wire, _)) = self.constants.iter().find(|(_, &v)| v == value) {
+            return Ok(wire);
+        }
+        let wire = self
+            .next
+            .ok_or_else(|| Error::Import("net ids exhausted".to_string()))?;
+        self.next = wire.checked_add(1);
+        self.constants.insert(wire, value);
+        Ok(wire)
+    }
+}
+
+impl Circuit {
+    /// Import a module of a Yosys JSON netlist (`write_json`) mapped to
+    /// internal gate cells, for example by `synth`
+    ///
+    /// Net ids are kept as wire ids. Constant bits become constant wires and
+    /// the circuit is named after the module.
+    pub fn from_yosys_json(text: &str, ports: &PortAssignment) -> Result<Circuit> {
+        let netlist: Netlist = serde_json::from_str(text)?;
+        let (name, module) = match &ports.module {
+            Some(name) => netlist
+                .modules
+                .get_key_value(name)
+                .ok_or_else(|| Error::Import(format!("no module `{name}`")))?,
+            None if netlist.modules.len() == 1 => netlist.modules.iter().next().unwrap(),
+            None => netlist
+                .modules
+                .iter()
+                .find(|(_, module)| module.is_top())
+                .ok_or_else(|| Error::Import("no top module, name one to import".to_string()))?,
+        };
+        if let Some(port) = module.port_names("inout").next() {
+            return Err(Error::Import(format!("inout port `{port}`")));
+        }
+
+        let input_ports: Vec<&str> = module.port_names("input").collect();
+        let (alice_ports, bob_ports) = ports.parties(&input_ports)?;
+        let output_ports: Vec<&str> = module.port_names("output").collect();
+        let out_ports = ports.outputs(&output_ports);
+
+        let largest_net = module
+            .ports
+            .values()
+            .flat_map(|port| &port.bits)
+            .chain(
+                module
+                    .cells
+                    .values()
+                    .flat_map(|cell| cell.connections.values().flatten()),
+            )
+            .filter_map(|bit| match bit {
+                Bit::Net(net) => Some(*net),
+                Bit::Constant(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let mut wires = Wires {
+            next: largest_net.checked_add(1),
+            constants: HashMap::new(),
+        };
+
+        let party_wires = |names: &[&str]| -> Result<Option<Vec<u32>>> {
+            let mut party = Vec::new();
+            for name in names {
+                for bit in module.port_bits(name, "input")? {
+                    match bit {
+                        Bit::Net(net) => party.push(*net),
+                        Bit::Constant(_) => {
+                            return Err(Error::Import(format!("constant bit in input `{name}`")))
+                        }
+                    }
+                }
+            }
+            Ok((!party.is_empty()).then_some(party))
+        };
+        let alice = party_wires(&alice_ports)?;
+        let bob = party_wires(&bob_ports)?;
+
+        let mut out = Vec::new();
+        for name in out_ports {
+            for bit in module.port_bits(name, "output")? {
+                out.push(wires.wire(bit)?);
+            }
+        }
+
+        let mut gates = Vec::new();
+        for (name, cell) in &module.cells {
+            let (function, input_ports) = cell_function(&cell.cell_type).ok_or_else(|| {
+                Error::Import(format!(
+                    "cell `{name}` has unsupported type `{}`",
+                    cell.cell_type
+                ))
+            })?;
+            let connection = |port: &str| match cell.connections.get(port).map(Vec::as_slice) {
+                Some([bit]) => Ok(bit),
+                _ => Err(Error::Import(format!(
+                    "cell `{name}` needs one bit on port {port}"
+                ))),
+            };
+            let inputs = input_ports
+                .iter()
+                .map(|port| wires.wire(connection(port)?))
+                .collect::<Result<Vec<u32>>>()?;
+            let Bit::Net(output) = connection("Y")? else {
+                return Err(Error::Import(format!("cell `{name}` drives a constant")));
+            };
+            gates.push(match function {
+                CellFunction::Gate(gate_type) => Gate::new(*output, gate_type, inputs),
+                CellFunction::Lut(function) => {
+                    let table = lut_table(inputs.len(), function);
+                    Gate::lut(*output, inputs, table)
+                }
+            });
+        }
+
+        let circuit = Circuit {
+            id: name.clone(),
+            gates,
+            alice,
+            bob,
+            out,
+            constants: wires.constants,
+        };
+        circuit.validate()?;
+        Ok(circuit)
+    }
+
+    /// Load a Yosys JSON netlist, see `from_yosys_json`
+    pub fn load_yosys_json<P: AsRef<Path>>(path: P, ports: &PortAssignment) -> Result<Circuit> {
+        Circuit::from_yosys_json(&fs::read_to_string(path)?, ports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::evaluate;
+
+    // Cells as written by `synth; write_json`, trimmed to the fields read
+    const NETLIST: &str = r#"{
+      "creator": "Yosys",
+      "modules": {
+        "helper": {
+          "ports": {},
+          "cells": {}
+        },
+        "top": {
+          "attributes": { "top": "00000000000000000000000000000001" },
+          "ports": {
+            "alice_a": { "direction": "input", "bits": [ 2, 3 ] },
+            "bob_b": { "direction": "input", "bits": [ 4, 5 ] },
+            "y": { "direction": "output", "bits": [ 6, 7, 8, 9, 11, "1" ] }
+          },
+          "cells": {
+            "$abc$1": {
+              "type": "$_MUX_",
+              "connections": { "A": [ 2 ], "B": [ 4 ], "S": [ 3 ], "Y": [ 6 ] }
+            },
+            "$abc$2": {
+              "type": "$_AOI3_",
+              "connections": { "A": [ 2 ], "B": [ 4 ], "C": [ 5 ], "Y": [ 7 ] }
+            },
+            "$abc$3": {
+              "type": "$_ANDNOT_",
+              "connections": { "A": [ 2 ], "B": [ "1" ], "Y": [ 8 ] }
+            },
+            "$abc$4": {
+              "type": "$_OAI4_",
+              "connections": { "A": [ 2 ], "B": [ 3 ], "C": [ 4 ], "D": [ 5 ], "Y": [ 9 ] }
+            },
+            "$abc$5": {
+              "type": "$_XNOR_",
+              "connections": { "A": [ 3 ], "B": [ 5 ], "Y": [ 10 ] }
+            },
+            "$abc$6": {
+              "type": "$_ORNOT_",
+              "connections": { "A": [ 10 ], "B": [ 2 ], "Y": [ 11 ] }
+            }
+          }
+        }
+      }
+    }"#;
+
+    #[test]
+    fn test_import_yosys_netlist() {
+        let circuit = Circuit::from_yosys_json(NETLIST, &PortAssignment::default()).unwrap();
+        assert_eq!(circuit.id, "top");
+        assert_eq!(circuit.alice, Some(vec![2, 3]));
+        assert_eq!(circuit.bob, Some(vec![4, 5]));
+        assert_eq!(circuit.out.len(), 6);
+        assert_eq!(circuit.constants.len(), 1);
+
+        for inputs in 0..16u8 {
+            let [a0, a1, b0, b1] = [0, 1, 2, 3].map(|i| (inputs >> i) & 1);
+            let outputs = evaluate(&circuit, &[a0, a1, b0, b1]);
+            let expected = vec![
+                if a1 == 1 { b0 } else { a0 },
+                1 ^ ((a0 & b0) | b1),
+                0,
+                1 ^ ((a0 | a1) & (b0 | b1)),
+                (1 ^ a1 ^ b1) | (1 ^ a0),
+                1,
+            ];
+            assert_eq!(outputs, expected, "{inputs:04b}");
+        }
+    }
+
+    #[test]
+    fn test_port_assignment() {
+        let ports: PortAssignment = serde_json::from_str(
+            r#"{"module": "top", "alice": ["bob_b"], "bob": ["alice_a"], "out": ["y"]}"#,
+        )
+        .unwrap();
+        let circuit = Circuit::from_yosys_json(NETLIST, &ports).unwrap();
+        assert_eq!(circuit.alice, Some(vec![4, 5]));
+        assert_eq!(circuit.bob, Some(vec![2, 3]));
+
+        // Every input port needs a party
+        let ports = PortAssignment {
+            alice: vec!["alice_a".to_string()],
+            ..PortAssignment::default()
+        };
+        let result = Circuit::from_yosys_json(NETLIST, &ports);
+        assert!(matches!(result, Err(Error::Import(reason)) if reason.contains("bob_b")));
+
+        let ports = PortAssignment {
+            module: Some("missing".to_string()),
+            ..PortAssignment::default()
+        };
+        assert!(matches!(
+            Circuit::from_yosys_json(NETLIST, &ports),
+            Err(Error::Import(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_cells() {
+        let netlist = NETLIST.replace("$_XNOR_", "$_DFF_P_");
+        let result = Circuit::from_yosys_json(&netlist, &PortAssignment::default());
+        assert!(matches!(result, Err(Error::Import(reason)) if reason.contains("$_DFF_P_")));
+
+        // Without the top attribute the module to import is ambiguous
+        let netlist = NETLIST.replace(r#""top": "00000000000000000000000000000001""#, "");
+        let result = Circuit::from_yosys_json(&netlist, &PortAssignment::default());
+        assert!(matches!(result, Err(Error::Import(_))));
+
+        // No wire id is left past the largest net for the constant
+        let netlist = NETLIST.replace("11", &u32::MAX.to_string());
+        let result = Circuit::from_yosys_json(&netlist, &PortAssignment::default());
+        assert!(matches!(result, Err(Error::Import(reason)) if reason.contains("exhausted")));
+    }
+}
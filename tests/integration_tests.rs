@@ -1,4 +1,6 @@
-use garbled_circuit_rs::{execute_secure_protocol, Circuit};
+use garbled_circuit_rs::{
+    execute_secure_protocol, Circuit, CircuitBuilder, Equivalence, Error, GateType,
+};
 use std::collections::HashMap;
 
 /// Test error handling for invalid inputs
@@ -9,23 +11,21 @@ fn test_invalid_input_size() {
         alice: Some(vec![1]),
         bob: Some(vec![2]),
         out: vec![3],
-        gates: vec![garbled_circuit_rs::circuit::Gate {
-            id: 3,
-            gate_type: "AND".to_string(),
-            inputs: vec![1, 2],
-        }],
+        constants: HashMap::new(),
+        gates: vec![garbled_circuit_rs::circuit::Gate::new(
+            3,
+            GateType::And,
+            vec![1, 2],
+        )],
     };
 
     // Test with wrong number of Alice inputs (missing input for wire 1)
     let alice_inputs = HashMap::new();
     let bob_inputs = [(2, 1)].iter().cloned().collect();
 
-    // This should panic due to missing inputs - the current implementation expects all inputs
-    let result =
-        std::panic::catch_unwind(|| execute_secure_protocol(circuit, alice_inputs, bob_inputs));
-
-    // The function currently panics with missing inputs (this is expected behavior)
-    assert!(result.is_err());
+    // Missing inputs are reported as an error rather than a panic
+    let result = execute_secure_protocol(circuit, alice_inputs, bob_inputs);
+    assert!(matches!(result, Err(Error::MissingInput(1))));
 }
 
 /// Test edge case with circuits that require Bob inputs
@@ -36,11 +36,12 @@ fn test_missing_bob_inputs() {
         alice: Some(vec![1]),
         bob: None,
         out: vec![2],
-        gates: vec![garbled_circuit_rs::circuit::Gate {
-            id: 2,
-            gate_type: "NOT".to_string(),
-            inputs: vec![1],
-        }],
+        constants: HashMap::new(),
+        gates: vec![garbled_circuit_rs::circuit::Gate::new(
+            2,
+            GateType::Not,
+            vec![1],
+        )],
     };
 
     let alice_inputs = [(1, 1)].iter().cloned().collect();
@@ -59,17 +60,10 @@ fn test_unary_operations_with_bob() {
         alice: Some(vec![1]),
         bob: Some(vec![2]), // Add dummy Bob input for OT protocol
         out: vec![4],
+        constants: HashMap::new(),
         gates: vec![
-            garbled_circuit_rs::circuit::Gate {
-                id: 3,
-                gate_type: "NOT".to_string(),
-                inputs: vec![1],
-            },
-            garbled_circuit_rs::circuit::Gate {
-                id: 4,
-                gate_type: "OR".to_string(), // Use Bob's input in final gate
-                inputs: vec![3, 2],
-            },
+            garbled_circuit_rs::circuit::Gate::new(3, GateType::Not, vec![1]),
+            garbled_circuit_rs::circuit::Gate::new(4, GateType::Or, vec![3, 2]), // Use Bob's input in final gate
         ],
     };
 
@@ -96,22 +90,11 @@ fn test_mixed_gate_types() {
         alice: Some(vec![1, 2]),
         bob: Some(vec![3, 4]),
         out: vec![7],
+        constants: HashMap::new(),
         gates: vec![
-            garbled_circuit_rs::circuit::Gate {
-                id: 5,
-                gate_type: "AND".to_string(),
-                inputs: vec![1, 2],
-            },
-            garbled_circuit_rs::circuit::Gate {
-                id: 6,
-                gate_type: "OR".to_string(),
-                inputs: vec![3, 4],
-            },
-            garbled_circuit_rs::circuit::Gate {
-                id: 7,
-                gate_type: "AND".to_string(),
-                inputs: vec![5, 6],
-            },
+            garbled_circuit_rs::circuit::Gate::new(5, GateType::And, vec![1, 2]),
+            garbled_circuit_rs::circuit::Gate::new(6, GateType::Or, vec![3, 4]),
+            garbled_circuit_rs::circuit::Gate::new(7, GateType::And, vec![5, 6]),
         ],
     };
 
@@ -132,22 +115,11 @@ fn test_multiple_inputs() {
         alice: Some(vec![1, 2]),
         bob: Some(vec![3, 4]),
         out: vec![7],
+        constants: HashMap::new(),
         gates: vec![
-            garbled_circuit_rs::circuit::Gate {
-                id: 5,
-                gate_type: "OR".to_string(),
-                inputs: vec![1, 2], // Alice's inputs combined
-            },
-            garbled_circuit_rs::circuit::Gate {
-                id: 6,
-                gate_type: "OR".to_string(),
-                inputs: vec![3, 4], // Bob's inputs combined
-            },
-            garbled_circuit_rs::circuit::Gate {
-                id: 7,
-                gate_type: "AND".to_string(),
-                inputs: vec![5, 6], // Final combination
-            },
+            garbled_circuit_rs::circuit::Gate::new(5, GateType::Or, vec![1, 2]), // Alice's inputs combined
+            garbled_circuit_rs::circuit::Gate::new(6, GateType::Or, vec![3, 4]), // Bob's inputs combined
+            garbled_circuit_rs::circuit::Gate::new(7, GateType::And, vec![5, 6]), // Final combination
         ],
     };
 
@@ -158,3 +130,192 @@ fn test_multiple_inputs() {
     let result = execute_secure_protocol(circuit, alice_inputs, bob_inputs).unwrap();
     assert_eq!(result.get(&7), Some(&1));
 }
+
+/// Test a constant carry-in through the full protocol
+#[test]
+fn test_constant_carry_in() {
+    // sum = a ^ b ^ cin, carry = (a & b) | (cin & (a ^ b)) with cin = 1
+    let mut circuit = Circuit {
+        id: "full_adder_cin".to_string(),
+        alice: Some(vec![1]),
+        bob: Some(vec![2]),
+        out: vec![5, 8],
+        constants: HashMap::new(),
+        gates: vec![
+            garbled_circuit_rs::circuit::Gate::new(4, GateType::Xor, vec![1, 2]),
+            garbled_circuit_rs::circuit::Gate::new(5, GateType::Xor, vec![4, 3]),
+            garbled_circuit_rs::circuit::Gate::new(6, GateType::And, vec![1, 2]),
+            garbled_circuit_rs::circuit::Gate::new(7, GateType::And, vec![3, 4]),
+            garbled_circuit_rs::circuit::Gate::new(8, GateType::Or, vec![6, 7]),
+        ],
+    };
+    circuit.constants.insert(3, 1);
+
+    for a in 0..2u8 {
+        for b in 0..2u8 {
+            let alice_inputs = [(1, a)].iter().cloned().collect();
+            let bob_inputs = [(2, b)].iter().cloned().collect();
+
+            let result =
+                execute_secure_protocol(circuit.clone(), alice_inputs, bob_inputs).unwrap();
+            let total = a + b + 1;
+            assert_eq!(result.get(&5), Some(&(total & 1)));
+            assert_eq!(result.get(&8), Some(&(total >> 1)));
+        }
+    }
+}
+
+/// Test a Bristol Fashion circuit through the full protocol
+#[test]
+fn test_bristol_fashion_adder() {
+    let circuit = Circuit::load_bristol("circuits/adder2.txt").unwrap();
+    assert_eq!(circuit.id, "adder2");
+
+    for a in 0..4u8 {
+        for b in 0..4u8 {
+            let alice_inputs = [(0, a & 1), (1, a >> 1)].iter().cloned().collect();
+            let bob_inputs = [(2, b & 1), (3, b >> 1)].iter().cloned().collect();
+
+            let result =
+                execute_secure_protocol(circuit.clone(), alice_inputs, bob_inputs).unwrap();
+            let sum = a + b;
+            let bits: Vec<u8> = circuit.out.iter().map(|wire| result[wire]).collect();
+            assert_eq!(bits, vec![sum & 1, (sum >> 1) & 1, sum >> 2], "{a} + {b}");
+        }
+    }
+}
+
+/// Test a builder-made circuit through the full protocol
+#[test]
+fn test_builder_max() {
+    let builder = CircuitBuilder::new();
+    let a = builder.alice_uint::<2>();
+    let b = builder.bob_uint::<2>();
+    builder.output_uint(&a.max(&b));
+    let circuit = builder.build("max").unwrap();
+
+    for x in 0..4u8 {
+        for y in 0..4u8 {
+            let alice_inputs = [(0, x & 1), (1, x >> 1)].iter().cloned().collect();
+            let bob_inputs = [(2, y & 1), (3, y >> 1)].iter().cloned().collect();
+
+            let result =
+                execute_secure_protocol(circuit.clone(), alice_inputs, bob_inputs).unwrap();
+            let bits: Vec<u8> = circuit.out.iter().map(|wire| result[wire]).collect();
+            let max = x.max(y);
+            assert_eq!(bits, vec![max & 1, max >> 1], "max({x}, {y})");
+        }
+    }
+}
+
+/// Test a circuit in the expression language through the full protocol
+#[test]
+fn test_expression_file() {
+    let circuit = Circuit::load_expression("circuits/compare.expr").unwrap();
+    assert_eq!(circuit.id, "compare");
+
+    for a in 0..4u8 {
+        for b in 0..4u8 {
+            let alice_inputs = [(0, a & 1), (1, a >> 1)].iter().cloned().collect();
+            let bob_inputs = [(2, b & 1), (3, b >> 1)].iter().cloned().collect();
+
+            let result =
+                execute_secure_protocol(circuit.clone(), alice_inputs, bob_inputs).unwrap();
+            let expected = (a == b || a & b & 1 == 1) as u8;
+            assert_eq!(result[&circuit.out[0]], expected, "{a} and {b}");
+        }
+    }
+}
+
+/// Test optimized bundled circuits give the same outputs through the full
+/// protocol
+#[test]
+fn test_optimized_circuits() {
+    for path in ["circuits/bool.json", "circuits/max.json"] {
+        // The protocol needs Bob to have inputs
+        let circuits = Circuit::from_json_file(path).unwrap();
+        for circuit in circuits.into_iter().filter(|circuit| circuit.bob.is_some()) {
+            let optimized = circuit.optimize().unwrap();
+            assert!(optimized.gates.len() <= circuit.gates.len());
+
+            let inputs: Vec<u32> = circuit.input_wires().collect();
+            for assignment in 0..1u32 << inputs.len() {
+                let bit = |wire: &u32| {
+                    let position = inputs.iter().position(|input| input == wire).unwrap();
+                    (*wire, ((assignment >> position) & 1) as u8)
+                };
+                let alice_inputs: HashMap<u32, u8> =
+                    circuit.alice.iter().flatten().map(bit).collect();
+                let bob_inputs: HashMap<u32, u8> = circuit.bob.iter().flatten().map(bit).collect();
+
+                let expected = execute_secure_protocol(
+                    circuit.clone(),
+                    alice_inputs.clone(),
+                    bob_inputs.clone(),
+                )
+                .unwrap();
+                let result =
+                    execute_secure_protocol(optimized.clone(), alice_inputs, bob_inputs).unwrap();
+                for wire in &circuit.out {
+                    assert_eq!(
+                        result[wire], expected[wire],
+                        "{} on {assignment}",
+                        circuit.id
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Test the plaintext evaluator agrees with the full protocol
+#[test]
+fn test_evaluate_plain_matches_protocol() {
+    let circuit = Circuit::load_bristol("circuits/adder2.txt").unwrap();
+    let inputs: Vec<u32> = circuit.input_wires().collect();
+    for assignment in 0..1u32 << inputs.len() {
+        let bits: HashMap<u32, u8> = inputs
+            .iter()
+            .enumerate()
+            .map(|(position, &wire)| (wire, ((assignment >> position) & 1) as u8))
+            .collect();
+        let party = |wires: &Option<Vec<u32>>| -> HashMap<u32, u8> {
+            wires
+                .iter()
+                .flatten()
+                .map(|wire| (*wire, bits[wire]))
+                .collect()
+        };
+
+        let expected =
+            execute_secure_protocol(circuit.clone(), party(&circuit.alice), party(&circuit.bob))
+                .unwrap();
+        assert_eq!(circuit.evaluate_plain(&bits).unwrap(), expected);
+    }
+}
+
+/// Imported, exported and optimized circuits stay equivalent, and different
+/// circuits come with a counterexample
+#[test]
+fn test_equivalence_checking() {
+    let circuit = Circuit::load_bristol("circuits/adder2.txt").unwrap();
+    let exported = Circuit::from_bristol("exported", &circuit.to_bristol().unwrap()).unwrap();
+    for other in [exported, circuit.optimize().unwrap()] {
+        assert_eq!(
+            circuit.check_equivalence(&other).unwrap(),
+            Equivalence::Equivalent
+        );
+    }
+
+    let circuits = Circuit::from_json_file("circuits/bool.json").unwrap();
+    let Equivalence::Different(counterexample) =
+        circuits[0].check_equivalence(&circuits[1]).unwrap()
+    else {
+        panic!("AND and OR differ");
+    };
+    assert_ne!(counterexample.alice, counterexample.bob);
+    assert_eq!(
+        (counterexample.left, counterexample.right),
+        (vec![0], vec![1])
+    );
+}